@@ -0,0 +1,305 @@
+//! Air quality alerts from the AirNow API.
+//!
+//! Current observations near each point are checked against the watch's
+//! AQI threshold, and each reading over it becomes an [`Alert`]. A reporting
+//! area's pollutant is only alerted once per day, and again only if it gets
+//! into a worse category than was already alerted that day.
+//!
+//! https://docs.airnowapi.org/CurrentObservationsByLatLon/docs
+
+use super::{
+    geo::Point,
+    model::{Alert, Category, Certainty, Geocode, MessageType, Severity, Urgency},
+    source::{APP_USER_AGENT, check_status},
+};
+use crate::{Error, config::AirQualityWatch};
+use chrono::{FixedOffset, NaiveDate, TimeDelta};
+use reqwest::{Client, header::USER_AGENT};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Mutex};
+
+/// How long an air quality alert is kept around after the reading
+const ALERT_LIFETIME: TimeDelta = TimeDelta::days(1);
+
+/// One pollutant's reading for a reporting area.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Observation {
+    /// Local date, ex "2026-10-17 "
+    date_observed: String,
+    /// Local hour, 0 to 23
+    hour_observed: u32,
+    /// Time zone abbreviation, ex "PST"
+    local_time_zone: String,
+    reporting_area: String,
+    state_code: String,
+    /// Pollutant, ex "PM2.5" or "O3"
+    parameter_name: String,
+    #[serde(rename = "AQI")]
+    aqi: i32,
+    category: AqiCategory,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AqiCategory {
+    /// 1 (Good) to 6 (Hazardous)
+    number: u8,
+    name: String,
+}
+
+/// UTC offset of the time zone abbreviations AirNow uses.
+fn utc_offset(zone: &str) -> Option<FixedOffset> {
+    let hours = match zone {
+        "EST" => -5, "EDT" => -4,
+        "CST" => -6, "CDT" => -5,
+        "MST" => -7, "MDT" => -6,
+        "PST" => -8, "PDT" => -7,
+        "AKST" => -9, "AKDT" => -8,
+        "HST" => -10,
+        "UTC" | "GMT" => 0,
+        _ => return None,
+    };
+    FixedOffset::east_opt(hours * 3600)
+}
+
+/// Severity and advice for each AQI category.
+fn category_advice(number: u8) -> (Severity, &'static str) {
+    match number {
+        0..=2 => (Severity::Minor, "Unusually sensitive people should consider reducing prolonged or heavy exertion outdoors."),
+        3 => (Severity::Moderate, "Sensitive groups should reduce prolonged or heavy exertion outdoors."),
+        4 => (Severity::Severe, "Everyone should reduce prolonged or heavy exertion outdoors, and sensitive groups should avoid it."),
+        5 => (Severity::Extreme, "Everyone should avoid prolonged or heavy exertion outdoors, and sensitive groups should stay indoors."),
+        _ => (Severity::Extreme, "Everyone should avoid all physical activity outdoors."),
+    }
+}
+
+impl Observation {
+    fn date(&self) -> Option<NaiveDate> {
+        NaiveDate::parse_from_str(self.date_observed.trim(), "%Y-%m-%d").ok()
+    }
+
+    /// Reporting area and pollutant, ex "Chico, CA:PM2.5"
+    fn key(&self) -> String {
+        format!("{}, {}:{}", self.reporting_area, self.state_code, self.parameter_name)
+    }
+
+    fn to_alert(&self) -> Option<Alert> {
+        let date = self.date()?;
+        let offset = utc_offset(&self.local_time_zone).unwrap_or(FixedOffset::east_opt(0)?);
+        let observed = date.and_hms_opt(self.hour_observed, 0, 0)?
+            .and_local_timezone(offset)
+            .single()?;
+
+        let area = format!("{}, {}", self.reporting_area, self.state_code);
+        let category = &self.category;
+        let (severity, advice) = category_advice(category.number);
+
+        Some(Alert {
+            // A new alert each day, or when the air gets into a worse category
+            id: format!("airnow:{}:{date}:{}", self.key(), category.number),
+            area_desc: area.clone(),
+            geocode: Geocode::default(),
+            sent: observed,
+            effective: observed,
+            onset: None,
+            expires: Some(observed + ALERT_LIFETIME),
+            ends: None,
+            message_type: MessageType::Alert,
+            category: Category::Env,
+            severity,
+            certainty: Certainty::Observed,
+            urgency: Urgency::Immediate,
+            event: String::from("Air Quality Alert"),
+            sender_name: String::from("AirNow"),
+            headline: Some(format!(
+                "{} air quality in {area}: AQI {} ({})",
+                category.name, self.aqi, self.parameter_name,
+            )),
+            description: format!(
+                "The {} AQI in {area} was {} ({}) as of {}:00 {}.",
+                self.parameter_name, self.aqi, category.name, self.hour_observed, self.local_time_zone,
+            ),
+            instruction: Some(advice.to_string()),
+            references: Vec::new(),
+            link: Some(String::from("https://www.airnow.gov")),
+            geometry: None,
+        })
+    }
+}
+
+/// Highest AQI category alerted for each reporting area and pollutant, and the day it was on.
+#[derive(Debug, Default)]
+pub struct Alerted(HashMap<String, (NaiveDate, u8)>);
+
+impl Alerted {
+    /// Check if a reading is in a worse category than was alerted for its area
+    /// and pollutant that day, remembering it if so.
+    fn is_worse(&mut self, observation: &Observation) -> bool {
+        let Some(date) = observation.date() else {
+            return false;
+        };
+        let number = observation.category.number;
+        let key = observation.key();
+        if self.0.get(&key).is_some_and(|&(day, highest)| day == date && highest >= number) {
+            return false;
+        }
+        self.0.insert(key, (date, number));
+        true
+    }
+}
+
+/// Alerts for every observation at or over the watch's threshold
+/// that is worse than was already alerted.
+pub fn over_threshold(observations: &[Observation], watch: &AirQualityWatch, alerted: &mut Alerted)
+-> Vec<Alert> {
+    observations.iter()
+        .filter(|o| o.aqi >= watch.min_aqi)
+        .filter(|o| alerted.is_worse(o))
+        .filter_map(Observation::to_alert)
+        .collect()
+}
+
+impl AirQualityWatch {
+    /// Key air quality alerts found for this watch are kept under, ex "AQI 101+ at 39.7300,-121.8400".
+    pub fn key(&self, points: &[Point]) -> String {
+        let points: Vec<String> = points.iter().map(Point::to_string).collect();
+        format!("AQI {}+ at {}", self.min_aqi, points.join(" "))
+    }
+}
+
+/// Strip the URL from request errors, as it holds the API key.
+fn hide_key(e: Error) -> Error {
+    match e.downcast::<reqwest::Error>() {
+        Ok(e) => Box::new(e.without_url()),
+        Err(e) => e,
+    }
+}
+
+/// Air quality source backed by the AirNow API.
+pub struct AirNowSource {
+    client: Client,
+    base_url: String,
+    /// Taken from `AIRNOW_API_KEY`, air quality can't be checked without one
+    api_key: Option<String>,
+    alerted: Mutex<Alerted>,
+}
+
+impl AirNowSource {
+    /// `base_url` is the root of the API, ex "https://www.airnowapi.org"
+    pub fn new(client: Client, base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            alerted: Mutex::default(),
+        }
+    }
+
+    /// Point at a new API root and key, still knowing what was already alerted.
+    pub fn reconfigure(&mut self, base_url: &str, api_key: Option<String>) {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self.api_key = api_key;
+    }
+
+    /// Air quality can't be checked without an API key.
+    pub fn has_key(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// Fetch the current readings near a point.
+    async fn observations(&self, point: Point, distance: u32, api_key: &str) -> Result<Vec<Observation>, Error> {
+        let url = format!(
+            "{}/aq/observation/latLong/current/?format=application/json\
+            &latitude={}&longitude={}&distance={distance}&API_KEY={api_key}",
+            self.base_url, point.lat, point.lon,
+        );
+        let response = self.client
+            .get(url)
+            .header(USER_AGENT, APP_USER_AGENT)
+            .send()
+            .await
+            .map_err(|e| hide_key(Box::new(e)))?;
+        let response = check_status(response).map_err(hide_key)?;
+        response.json().await.map_err(|e| hide_key(Box::new(e)))
+    }
+
+    /// Fetch alerts for poor air quality near any of `points`.
+    pub async fn fetch_air_quality(&self, watch: &AirQualityWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        let Some(api_key) = &self.api_key else {
+            return Err("AIRNOW_API_KEY isn't set, so air quality can't be checked".into());
+        };
+
+        // Nearby points often share a reporting area
+        let mut alerts = HashMap::new();
+        for &point in points {
+            let observations = self.observations(point, watch.distance_miles, api_key).await?;
+            let found = over_threshold(&observations, watch, &mut self.alerted.lock().unwrap());
+            for alert in found {
+                alerts.insert(alert.id.clone(), alert);
+            }
+        }
+        Ok(alerts.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBSERVATIONS: &str = include_str!("fixtures/airnow.json");
+
+    fn watch(min_aqi: i32) -> AirQualityWatch {
+        AirQualityWatch { min_aqi, distance_miles: 25, points: Vec::new() }
+    }
+
+    #[test]
+    fn readings_over_the_threshold_become_alerts() {
+        let observations: Vec<Observation> = serde_json::from_str(OBSERVATIONS).unwrap();
+        assert!(over_threshold(&observations, &watch(200), &mut Alerted::default()).is_empty());
+
+        let alerts = over_threshold(&observations, &watch(101), &mut Alerted::default());
+        let [alert] = &alerts[..] else {
+            panic!("expected one alert, got {alerts:?}");
+        };
+        assert_eq!(alert.id, "airnow:Chico, CA:PM2.5:2026-10-17:4");
+        assert_eq!(alert.severity, Severity::Severe);
+        assert_eq!(alert.category, Category::Env);
+        assert_eq!(alert.sent.to_rfc3339(), "2026-10-17T11:00:00-07:00");
+        assert_eq!(alert.headline.as_deref(), Some("Unhealthy air quality in Chico, CA: AQI 162 (PM2.5)"));
+    }
+
+    #[test]
+    fn every_reading_can_alert() {
+        let observations: Vec<Observation> = serde_json::from_str(OBSERVATIONS).unwrap();
+        assert_eq!(over_threshold(&observations, &watch(0), &mut Alerted::default()).len(), 2);
+    }
+
+    fn reading(date: &str, category: u8) -> Observation {
+        Observation {
+            date_observed: date.to_string(),
+            hour_observed: 11,
+            local_time_zone: String::from("PDT"),
+            reporting_area: String::from("Chico"),
+            state_code: String::from("CA"),
+            parameter_name: String::from("PM2.5"),
+            aqi: 50 * category as i32,
+            category: AqiCategory { number: category, name: String::new() },
+        }
+    }
+
+    #[test]
+    fn only_worse_readings_alert_again() {
+        let mut alerted = Alerted::default();
+        let mut check = |date: &str, category: u8| {
+            over_threshold(&[reading(date, category)], &watch(101), &mut alerted).len()
+        };
+        assert_eq!(check("2026-10-17", 5), 1);
+        assert_eq!(check("2026-10-17", 5), 0);
+        // Improving doesn't alert, even though it is a new category
+        assert_eq!(check("2026-10-17", 4), 0);
+        assert_eq!(check("2026-10-17", 6), 1);
+        assert_eq!(check("2026-10-18", 4), 1);
+    }
+}
@@ -0,0 +1,342 @@
+//! Alerts from Common Alerting Protocol (CAP) 1.2 feeds, for places
+//! outside the US (ex MeteoAlarm, Environment Canada).
+//!
+//! A feed is either an Atom feed whose entries link to CAP documents,
+//! or a single CAP document. Each document is parsed into the same
+//! [`Alert`] model as NWS alerts.
+//!
+//! https://docs.oasis-open.org/emergency/cap/v1.2/CAP-v1.2.html
+
+use super::{
+    geo::Geometry,
+    model::{Alert, Category, Certainty, Geocode, MessageType, Reference, Severity, Urgency},
+    source::{APP_USER_AGENT, Throttled, check_status},
+};
+use crate::{Error, config::CapFeed};
+use chrono::{DateTime, FixedOffset};
+use quick_xml::{Reader, events::Event};
+use reqwest::{Client, Url, header::USER_AGENT};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Mutex};
+use tracing::error;
+
+/// Language picked from alerts that come in several, unless a feed sets its own
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// An Atom feed, only as far as needed to find its CAP documents.
+#[derive(Debug, Deserialize)]
+struct Feed {
+    #[serde(rename = "entry", default)]
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(rename = "link", default)]
+    links: Vec<Link>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Link {
+    #[serde(rename = "@href")]
+    href: String,
+    #[serde(rename = "@type")]
+    kind: Option<String>,
+}
+
+impl Entry {
+    /// URL of the CAP document the entry is for, which may be relative to the feed.
+    fn cap_link(&self) -> Option<&str> {
+        self.links.iter()
+            .find(|l| l.kind.as_deref().is_some_and(|k| k.contains("cap")))
+            .map(|l| l.href.as_str())
+    }
+}
+
+/// An element whose text is parsed as `T`. Enums need this, as otherwise
+/// their variants are taken from element names.
+#[derive(Debug, Deserialize)]
+struct Text<T> {
+    #[serde(rename = "$text")]
+    value: T,
+}
+
+/// CAP `<alert>` element.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CapAlert {
+    identifier: String,
+    sender: String,
+    sent: DateTime<FixedOffset>,
+    msg_type: Text<MessageType>,
+    /// Space separated "sender,identifier,sent" triples
+    references: Option<String>,
+    #[serde(rename = "info", default)]
+    infos: Vec<Info>,
+}
+
+/// CAP `<info>` element, one per language.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Info {
+    language: Option<String>,
+    #[serde(rename = "category", default)]
+    categories: Vec<Text<Category>>,
+    event: String,
+    urgency: Text<Urgency>,
+    severity: Text<Severity>,
+    certainty: Text<Certainty>,
+    effective: Option<DateTime<FixedOffset>>,
+    onset: Option<DateTime<FixedOffset>>,
+    expires: Option<DateTime<FixedOffset>>,
+    sender_name: Option<String>,
+    headline: Option<String>,
+    description: Option<String>,
+    instruction: Option<String>,
+    web: Option<String>,
+    #[serde(rename = "area", default)]
+    areas: Vec<Area>,
+}
+
+/// CAP `<area>` element.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Area {
+    area_desc: String,
+    /// Space separated "lat,lon" pairs
+    #[serde(rename = "polygon", default)]
+    polygons: Vec<String>,
+    #[serde(rename = "geocode", default)]
+    geocodes: Vec<ValuePair>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValuePair {
+    value_name: String,
+    value: String,
+}
+
+/// Name of the first element in a document, without any namespace prefix.
+fn root_element(xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event().ok()? {
+            Event::Start(e) | Event::Empty(e) => {
+                return Some(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+            }
+            Event::Eof => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Parse a CAP polygon of "lat,lon" pairs into a GeoJSON ring.
+fn parse_ring(polygon: &str) -> Option<Vec<[f64; 2]>> {
+    polygon.split_whitespace()
+        .map(|pair| {
+            let (lat, lon) = pair.split_once(',')?;
+            Some([lon.parse().ok()?, lat.parse().ok()?])
+        })
+        .collect()
+}
+
+/// Parse CAP references, skipping any that are malformed.
+fn parse_references(references: &str) -> Vec<Reference> {
+    references.split_whitespace()
+        .filter_map(|r| {
+            let mut parts = r.splitn(3, ',');
+            let (sender, identifier, sent) = (parts.next()?, parts.next()?, parts.next()?);
+            Some(Reference {
+                url: String::new(),
+                identifier: identifier.to_string(),
+                sender: sender.to_string(),
+                sent: DateTime::parse_from_rfc3339(sent).ok()?,
+            })
+        })
+        .collect()
+}
+
+impl CapAlert {
+    /// Convert to an [`Alert`], using the info block in `language` if there is one.
+    /// Returns `None` for alerts without any info.
+    fn into_alert(self, language: &str, url: &str) -> Option<Alert> {
+        let mut infos = self.infos;
+        if infos.is_empty() {
+            return None;
+        }
+        let chosen = infos.iter()
+            .position(|i| {
+                // CAP defaults to US English when no language is given
+                let lang = i.language.as_deref().unwrap_or("en-US").to_lowercase();
+                lang.starts_with(&language.to_lowercase())
+            })
+            .unwrap_or(0);
+        let info = infos.swap_remove(chosen);
+
+        let area_desc = info.areas.iter()
+            .map(|a| a.area_desc.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let ugc = info.areas.iter()
+            .flat_map(|a| &a.geocodes)
+            .filter(|g| g.value_name == "UGC")
+            .map(|g| g.value.clone())
+            .collect();
+        let mut rings: Vec<Vec<[f64; 2]>> = info.areas.iter()
+            .flat_map(|a| &a.polygons)
+            .filter_map(|p| parse_ring(p))
+            .collect();
+        let geometry = match rings.len() {
+            0 => None,
+            1 => Some(Geometry::Polygon(vec![rings.remove(0)])),
+            _ => Some(Geometry::MultiPolygon(rings.into_iter().map(|r| vec![r]).collect())),
+        };
+
+        Some(Alert {
+            id: self.identifier,
+            area_desc,
+            geocode: Geocode { ugc },
+            sent: self.sent,
+            effective: info.effective.unwrap_or(self.sent),
+            onset: info.onset,
+            expires: info.expires,
+            ends: None,
+            message_type: self.msg_type.value,
+            category: info.categories.first().map_or(Category::Other, |c| c.value),
+            severity: info.severity.value,
+            certainty: info.certainty.value,
+            urgency: info.urgency.value,
+            event: info.event,
+            sender_name: info.sender_name.unwrap_or(self.sender),
+            headline: info.headline,
+            description: info.description.unwrap_or_default(),
+            instruction: info.instruction,
+            references: self.references.as_deref().map(parse_references).unwrap_or_default(),
+            link: Some(info.web.unwrap_or_else(|| url.to_string())),
+            geometry,
+        })
+    }
+}
+
+/// Parse a CAP document into an [`Alert`], logging any that fail to parse.
+pub fn parse_alert(xml: &str, language: &str, url: &str) -> Option<Alert> {
+    match quick_xml::de::from_str::<CapAlert>(xml) {
+        Ok(cap) => cap.into_alert(language, url),
+        Err(e) => {
+            error!("Failed to parse CAP alert {url}: {e}");
+            None
+        }
+    }
+}
+
+/// Alert source backed by CAP feeds.
+pub struct CapSource {
+    client: Client,
+    /// Parsed CAP documents, keyed by feed and then document URL.
+    /// Documents don't change, so each is only downloaded once.
+    cache: Mutex<HashMap<String, HashMap<String, Option<Alert>>>>,
+}
+
+impl CapSource {
+    pub fn new(client: Client) -> Self {
+        Self { client, cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// GET a URL as text.
+    async fn get(&self, url: &str) -> Result<String, Error> {
+        let response = self.client
+            .get(url)
+            .header(USER_AGENT, APP_USER_AGENT)
+            .send()
+            .await?;
+        Ok(check_status(response)?.text().await?)
+    }
+
+    /// Fetch the alerts currently in a feed.
+    pub async fn fetch_feed(&self, feed: &CapFeed) -> Result<Vec<Alert>, Error> {
+        let language = feed.language.as_deref().unwrap_or(DEFAULT_LANGUAGE);
+        let body = self.get(&feed.url).await?;
+        if root_element(&body).as_deref() == Some("alert") {
+            return Ok(parse_alert(&body, language, &feed.url).into_iter().collect());
+        }
+
+        let atom: Feed = quick_xml::de::from_str(&body)?;
+        let base = Url::parse(&feed.url)?;
+        let links: Vec<String> = atom.entries.iter()
+            .filter_map(Entry::cap_link)
+            .filter_map(|link| base.join(link).ok())
+            .map(String::from)
+            .collect();
+
+        // Forget documents that have dropped out of the feed
+        let mut known = self.cache.lock().unwrap().remove(&feed.url).unwrap_or_default();
+        known.retain(|url, _| links.contains(url));
+
+        let mut alerts = Vec::new();
+        let mut result = Ok(());
+        for link in links {
+            if let Some(alert) = known.get(&link) {
+                alerts.extend(alert.clone());
+                continue;
+            }
+            match self.get(&link).await {
+                Ok(xml) => {
+                    let alert = parse_alert(&xml, language, &link);
+                    alerts.extend(alert.clone());
+                    known.insert(link, alert);
+                }
+                // Don't keep making requests once asked to slow down
+                Err(e) if e.is::<Throttled>() => {
+                    result = Err(e);
+                    break;
+                }
+                // Left out of the cache so it's tried again next time
+                Err(e) => error!("Failed to fetch CAP alert {link}: {e}"),
+            }
+        }
+        self.cache.lock().unwrap().insert(feed.url.clone(), known);
+
+        result.map(|()| alerts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Point;
+
+    const ALERT: &str = include_str!("fixtures/cap_alert.xml");
+    const CANCEL: &str = include_str!("fixtures/cap_cancel.xml");
+
+    #[test]
+    fn alerts_are_shown_in_the_chosen_language() {
+        let alert = parse_alert(ALERT, "en", "https://example.com/cap.xml").expect("parsed");
+        assert_eq!(alert.event, "gale-force gusts");
+        assert_eq!(alert.severity, Severity::Moderate);
+        assert_eq!(alert.category, Category::Met);
+        assert_eq!(alert.link.as_deref(), Some("https://www.wettergefahren.de"));
+        assert!(alert.geometry.expect("polygon").contains(Point { lat: 53.55, lon: 10.0 }));
+
+        let alert = parse_alert(ALERT, "de", "https://example.com/cap.xml").expect("parsed");
+        assert_eq!(alert.event, "STURMBÖEN");
+        // Without a web page the alert links to its CAP document
+        assert_eq!(alert.link.as_deref(), Some("https://example.com/cap.xml"));
+    }
+
+    #[test]
+    fn prefixed_documents_and_references_are_parsed() {
+        let alert = parse_alert(CANCEL, "en", "https://example.com/cap.xml").expect("parsed");
+        assert_eq!(alert.message_type, MessageType::Cancel);
+        assert_eq!(alert.references.len(), 1);
+        assert_eq!(alert.references[0].identifier, "2.49.0.0.276.0.DWD.PVW.1760688000000.wind-001");
+        assert_eq!(alert.effective, alert.sent);
+    }
+
+    #[test]
+    fn documents_are_told_apart_from_feeds() {
+        assert_eq!(root_element(ALERT).as_deref(), Some("alert"));
+        assert_eq!(root_element(CANCEL).as_deref(), Some("alert"));
+        assert_eq!(root_element(include_str!("fixtures/cap_feed.xml")).as_deref(), Some("feed"));
+    }
+}
@@ -0,0 +1,207 @@
+//! "I'm safe" check-ins on posted alerts.
+//!
+//! Alerts posted to a channel carry a row of buttons members can press to
+//! check in. Each member's latest answer is kept in the db file per alert
+//! message, so moderators can see who still needs help, for as long as
+//! the alert is kept in the history.
+
+use super::history::ArchivedAlert;
+use crate::db::Database;
+use poise::serenity_prelude::{
+    self as serenity, ButtonStyle, ChannelId, ComponentInteractionCollector, CreateActionRow,
+    CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage, MessageId, UserId,
+};
+use poise::futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Start of the custom ID of every check-in button, followed by the answer
+const CUSTOM_ID_PREFIX: &str = "alert-checkin:";
+
+/// An answer a member can give to an alert.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckIn {
+    Acknowledged,
+    Safe,
+    NeedHelp,
+}
+
+impl CheckIn {
+    /// Every answer, in the order the buttons are shown
+    pub const ALL: [CheckIn; 3] = [CheckIn::Acknowledged, CheckIn::Safe, CheckIn::NeedHelp];
+
+    fn key(self) -> &'static str {
+        match self {
+            CheckIn::Acknowledged => "acknowledged",
+            CheckIn::Safe => "safe",
+            CheckIn::NeedHelp => "need_help",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CheckIn::Acknowledged => "Acknowledged",
+            CheckIn::Safe => "I'm safe",
+            CheckIn::NeedHelp => "Need help",
+        }
+    }
+
+    fn custom_id(self) -> String {
+        format!("{CUSTOM_ID_PREFIX}{}", self.key())
+    }
+
+    fn from_custom_id(custom_id: &str) -> Option<Self> {
+        let key = custom_id.strip_prefix(CUSTOM_ID_PREFIX)?;
+        Self::ALL.into_iter().find(|c| c.key() == key)
+    }
+
+    /// Sent back to the member, only visible to them.
+    fn reply(self) -> &'static str {
+        match self {
+            CheckIn::Acknowledged => "Thanks, you've acknowledged this alert.",
+            CheckIn::Safe => "Glad you're safe! Your check-in has been recorded.",
+            CheckIn::NeedHelp => "Moderators can now see that you need help. \
+                If you are in immediate danger, call your local emergency number.",
+        }
+    }
+}
+
+/// Buttons shown under a posted alert.
+pub fn check_in_buttons() -> CreateActionRow {
+    CreateActionRow::Buttons(CheckIn::ALL.into_iter()
+        .map(|c| {
+            let style = match c {
+                CheckIn::Acknowledged => ButtonStyle::Secondary,
+                CheckIn::Safe => ButtonStyle::Success,
+                CheckIn::NeedHelp => ButtonStyle::Danger,
+            };
+            CreateButton::new(c.custom_id()).style(style).label(c.label())
+        })
+        .collect())
+}
+
+/// Check-ins on one alert message, saved in the db file.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CheckIns {
+    /// Channel the alert message is in
+    pub channel_id: ChannelId,
+    /// Each member's latest answer
+    pub members: HashMap<UserId, CheckIn>,
+}
+
+impl CheckIns {
+    /// How many members gave each answer, in the order the buttons are shown.
+    pub fn tally(&self) -> Vec<(CheckIn, usize)> {
+        CheckIn::ALL.into_iter()
+            .map(|c| (c, self.members.values().filter(|&&m| m == c).count()))
+            .collect()
+    }
+
+    /// Members whose latest answer is that they need help.
+    pub fn need_help(&self) -> Vec<UserId> {
+        let mut users: Vec<UserId> = self.members.iter()
+            .filter(|(_, c)| **c == CheckIn::NeedHelp)
+            .map(|(&user_id, _)| user_id)
+            .collect();
+        users.sort();
+        users
+    }
+}
+
+/// Record a member's answer to an alert message, replacing any earlier one.
+fn record(
+    check_ins: &mut HashMap<MessageId, CheckIns>,
+    (channel_id, message_id): (ChannelId, MessageId),
+    user_id: UserId,
+    check_in: CheckIn,
+) {
+    check_ins.entry(message_id)
+        .or_insert_with(|| CheckIns { channel_id, members: HashMap::new() })
+        .members
+        .insert(user_id, check_in);
+}
+
+/// Drop check-ins on messages of alerts that are no longer in the history.
+pub fn prune(check_ins: &mut HashMap<MessageId, CheckIns>, history: &[ArchivedAlert]) {
+    let archived: HashSet<(ChannelId, MessageId)> = history.iter()
+        .flat_map(|a| a.messages.iter().copied())
+        .collect();
+    check_ins.retain(|&message_id, c| archived.contains(&(c.channel_id, message_id)));
+}
+
+/// Handle check-in button presses on alert messages for as long as the bot runs.
+pub async fn collect_check_ins(ctx: serenity::Context, db: Arc<Mutex<Database>>, db_path: String) {
+    let mut presses = ComponentInteractionCollector::new(&ctx)
+        .filter(|mci| mci.data.custom_id.starts_with(CUSTOM_ID_PREFIX))
+        .stream();
+
+    while let Some(mci) = presses.next().await {
+        let Some(check_in) = CheckIn::from_custom_id(&mci.data.custom_id) else {
+            continue;
+        };
+
+        {
+            let mut db = db.lock().await;
+            record(&mut db.check_ins, (mci.channel_id, mci.message.id), mci.user.id, check_in);
+            if let Err(e) = db.save(&db_path).await {
+                warn!("Failed to save check-in to db file: {e}");
+            }
+        }
+
+        let reply = CreateInteractionResponseMessage::new()
+            .ephemeral(true)
+            .content(check_in.reply());
+        if let Err(e) = mci.create_response(&ctx, CreateInteractionResponse::Message(reply)).await {
+            warn!("Failed to reply to check-in from user {}: {e}", mci.user.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::nws::FeatureCollection;
+
+    #[test]
+    fn buttons_round_trip_through_their_custom_ids() {
+        for check_in in CheckIn::ALL {
+            assert_eq!(CheckIn::from_custom_id(&check_in.custom_id()), Some(check_in));
+        }
+        assert_eq!(CheckIn::from_custom_id("alert-checkin:maybe"), None);
+        assert_eq!(CheckIn::from_custom_id("safe"), None);
+    }
+
+    #[test]
+    fn only_the_latest_answer_counts() {
+        let message = (ChannelId::new(1), MessageId::new(2));
+        let mut check_ins = HashMap::new();
+        record(&mut check_ins, message, UserId::new(10), CheckIn::NeedHelp);
+        record(&mut check_ins, message, UserId::new(11), CheckIn::NeedHelp);
+        record(&mut check_ins, message, UserId::new(12), CheckIn::Acknowledged);
+        record(&mut check_ins, message, UserId::new(10), CheckIn::Safe);
+
+        let tally = &check_ins[&MessageId::new(2)];
+        assert_eq!(tally.tally(), [
+            (CheckIn::Acknowledged, 1),
+            (CheckIn::Safe, 1),
+            (CheckIn::NeedHelp, 1),
+        ]);
+        assert_eq!(tally.need_help(), [UserId::new(11)]);
+    }
+
+    #[test]
+    fn check_ins_go_with_their_alert() {
+        let mut check_ins = HashMap::new();
+        record(&mut check_ins, (ChannelId::new(1), MessageId::new(2)), UserId::new(10), CheckIn::Safe);
+        record(&mut check_ins, (ChannelId::new(1), MessageId::new(3)), UserId::new(10), CheckIn::Safe);
+
+        let active: FeatureCollection = serde_json::from_str(include_str!("fixtures/active.json")).unwrap();
+        let alert = active.into_alerts().remove(0);
+        let history = [ArchivedAlert::new(&alert, &[(ChannelId::new(1), MessageId::new(3))])];
+        prune(&mut check_ins, &history);
+        assert_eq!(check_ins.keys().collect::<Vec<_>>(), [&MessageId::new(3)]);
+    }
+}
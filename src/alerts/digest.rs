@@ -0,0 +1,44 @@
+//! Collects alerts for routes in digest mode, so they can be
+//! posted as one summary at the times the route is set to.
+
+use super::model::{Alert, MessageType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Alerts waiting for a route's next digest, saved in the db file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PendingDigest {
+    /// When the last digest was posted, or the route started collecting alerts
+    pub since: DateTime<Utc>,
+    /// Oldest first
+    pub alerts: Vec<Alert>,
+}
+
+impl PendingDigest {
+    pub fn new(since: DateTime<Utc>) -> Self {
+        Self { since, alerts: Vec::new() }
+    }
+
+    /// Add an alert to the next digest. Updates replace the version they reference,
+    /// and cancellations remove it so it is never posted.
+    /// Returns whether the digest changed.
+    pub fn push(&mut self, alert: Alert) -> bool {
+        if self.alerts.iter().any(|a| a.id == alert.id) {
+            return false;
+        }
+
+        let referenced: HashSet<&str> = alert.references.iter()
+            .map(|r| r.identifier.as_str())
+            .collect();
+        let old_len = self.alerts.len();
+        self.alerts.retain(|a| !referenced.contains(a.id.as_str()));
+        let removed = self.alerts.len() != old_len;
+
+        if alert.message_type == MessageType::Cancel {
+            return removed;
+        }
+        self.alerts.push(alert);
+        true
+    }
+}
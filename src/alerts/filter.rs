@@ -0,0 +1,40 @@
+//! Decides which alerts are wanted by the configured filters
+//! and which roles they mention.
+
+use super::model::{Alert, MessageType};
+use crate::config::{AlertFilter, AlertMention};
+
+impl AlertFilter {
+    /// Check if an alert passes every configured filter.
+    pub fn matches(&self, alert: &Alert) -> bool {
+        (self.alert_types.is_empty() || self.alert_types.contains(&alert.category))
+            && (self.events.is_empty() || self.events.contains(&alert.event))
+            && !self.exclude_events.contains(&alert.event)
+            && self.meets_thresholds(alert)
+    }
+
+    /// Check the minimum severity, urgency and certainty.
+    /// Cancellations always pass so that a posted alert can still be
+    /// marked cancelled when NWS downgrades it on the way out.
+    fn meets_thresholds(&self, alert: &Alert) -> bool {
+        if alert.message_type == MessageType::Cancel {
+            return true;
+        }
+
+        self.min_severity.is_none_or(|min| alert.severity >= min)
+            && self.min_urgency.is_none_or(|min| alert.urgency >= min)
+            && self.min_certainty.is_none_or(|min| alert.certainty >= min)
+    }
+}
+
+impl AlertMention {
+    /// Check if an alert should mention this role.
+    pub fn matches(&self, alert: &Alert) -> bool {
+        if self.events.is_empty() && self.min_severity.is_none() {
+            return true;
+        }
+
+        self.events.contains(&alert.event)
+            || self.min_severity.is_some_and(|min| alert.severity >= min)
+    }
+}
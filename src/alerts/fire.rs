@@ -0,0 +1,120 @@
+//! Fire weather specifics pulled out of Red Flag Warning and Fire Weather Watch text.
+//!
+//! NWS fire weather products list their details as bullets, ex
+//! "* WIND...North 15 to 25 mph with gusts up to 45 mph." These are
+//! shown as their own embed fields so they can be read at a glance.
+
+use super::model::{Alert, Category};
+
+/// Events that are parsed for fire weather, on top of any Fire category alert
+const FIRE_EVENTS: [&str; 2] = ["Red Flag Warning", "Fire Weather Watch"];
+
+/// Bullet headings for each detail, as offices word them differently
+const WIND: [&str; 3] = ["WIND", "WINDS", "WINDS AND GUSTS"];
+const HUMIDITY: [&str; 3] = ["HUMIDITY", "RELATIVE HUMIDITY", "RH"];
+const TIMING: [&str; 3] = ["WHEN", "TIMING", "TIME"];
+/// Newer products fold wind and humidity into a single bullet
+const CONDITIONS: [&str; 1] = ["WHAT"];
+
+/// Fire weather details of an alert. Anything not in the text is left out.
+#[derive(Debug, Default, PartialEq)]
+pub struct FireWeather {
+    pub wind: Option<String>,
+    pub humidity: Option<String>,
+    pub timing: Option<String>,
+    /// Only kept when wind and humidity don't have their own bullets
+    pub conditions: Option<String>,
+}
+
+/// Split a description into its "* HEADING...text" bullets,
+/// joining bullets that wrap over several lines.
+fn bullets(description: &str) -> Vec<(String, String)> {
+    let mut bullets: Vec<(String, String)> = Vec::new();
+    let mut in_bullet = false;
+    for line in description.lines().map(str::trim) {
+        if let Some(bullet) = line.strip_prefix("* ")
+            && let Some((heading, text)) = bullet.split_once("...") {
+            bullets.push((heading.trim().to_uppercase(), text.trim().to_string()));
+            in_bullet = true;
+        } else if line.is_empty() {
+            in_bullet = false;
+        } else if in_bullet && let Some((_, text)) = bullets.last_mut() {
+            text.push(' ');
+            text.push_str(line);
+        }
+    }
+    bullets
+}
+
+/// Fire weather details of an alert, if it is about fire weather and lists any.
+pub fn fire_weather(alert: &Alert) -> Option<FireWeather> {
+    if alert.category != Category::Fire && !FIRE_EVENTS.contains(&alert.event.as_str()) {
+        return None;
+    }
+
+    let bullets = bullets(&alert.description);
+    let find = |headings: &[&str]| bullets.iter()
+        .find(|(heading, _)| headings.contains(&heading.as_str()))
+        .map(|(_, text)| text.trim_end_matches('.').to_string());
+
+    let mut fire = FireWeather {
+        wind: find(&WIND),
+        humidity: find(&HUMIDITY),
+        timing: find(&TIMING),
+        conditions: None,
+    };
+    if fire.wind.is_none() && fire.humidity.is_none() {
+        fire.conditions = find(&CONDITIONS);
+    }
+
+    (fire != FireWeather::default()).then_some(fire)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::nws::FeatureCollection;
+
+    fn red_flag(description: &str) -> Alert {
+        let collection: FeatureCollection =
+            serde_json::from_str(include_str!("fixtures/active.json")).unwrap();
+        let alert = collection.into_alerts().remove(0);
+        Alert { description: description.to_string(), ..alert }
+    }
+
+    #[test]
+    fn bullets_are_parsed_from_the_description() {
+        let alert = red_flag(
+            "The National Weather Service has issued a Red Flag Warning.\n\n\
+            * AFFECTED AREA...Fire Weather Zones 215 and 278.\n\n\
+            * WIND...North 15 to 25 mph with gusts up\nto 45 mph.\n\n\
+            * HUMIDITY...8 to 15 percent.\n\n\
+            * TIMING...From 11 AM Saturday to 8 PM Sunday.",
+        );
+        assert_eq!(fire_weather(&alert), Some(FireWeather {
+            wind: Some(String::from("North 15 to 25 mph with gusts up to 45 mph")),
+            humidity: Some(String::from("8 to 15 percent")),
+            timing: Some(String::from("From 11 AM Saturday to 8 PM Sunday")),
+            conditions: None,
+        }));
+    }
+
+    #[test]
+    fn what_bullets_stand_in_for_wind_and_humidity() {
+        let alert = red_flag(
+            "* WHAT...Gusty north winds and relative humidity as low as 8 percent.\n\n\
+            * WHERE...Butte County Foothills.\n\n\
+            * WHEN...Until 8 PM PDT Sunday.",
+        );
+        let fire = fire_weather(&alert).expect("fire weather");
+        assert_eq!(fire.conditions.as_deref(), Some("Gusty north winds and relative humidity as low as 8 percent"));
+        assert_eq!(fire.timing.as_deref(), Some("Until 8 PM PDT Sunday"));
+    }
+
+    #[test]
+    fn other_alerts_are_left_alone() {
+        let mut alert = red_flag("* WIND...North 20 mph.");
+        alert.event = String::from("Wind Advisory");
+        assert_eq!(fire_weather(&alert), None);
+    }
+}
@@ -0,0 +1,246 @@
+//! Geographic matching of alerts against routes.
+//!
+//! Many warnings (ex tornado, flash flood) only cover part of a zone.
+//! Routes with points or a polygon only get alerts whose geometry
+//! covers them, rather than every alert in their zones.
+//!
+//! Coordinates follow GeoJSON, so positions are [longitude, latitude].
+
+use super::model::Alert;
+use crate::config::AlertRoute;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt};
+
+/// A location given by latitude and longitude.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Point {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+/// Mean radius of the Earth
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+impl Point {
+    /// Great circle distance to another point, using the haversine formula.
+    pub fn distance_km(&self, other: Point) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.lon - self.lon).to_radians();
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+impl fmt::Display for Point {
+    /// Formatted the way the NWS API takes points, ex "39.7300,-121.8400"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4},{:.4}", self.lat, self.lon)
+    }
+}
+
+/// A closed ring of [longitude, latitude] positions.
+type Ring = Vec<[f64; 2]>;
+
+/// GeoJSON geometry of an alert, or of a route's area.
+/// The first ring of a polygon is its outline, any others are holes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Polygon(Vec<Ring>),
+    MultiPolygon(Vec<Vec<Ring>>),
+}
+
+/// Check if a point is inside a ring using ray casting.
+fn ring_contains(ring: &[[f64; 2]], point: Point) -> bool {
+    let (x, y) = (point.lon, point.lat);
+    let mut inside = false;
+    let mut j = ring.len().wrapping_sub(1);
+    for i in 0..ring.len() {
+        let ([xi, yi], [xj, yj]) = (ring[i], ring[j]);
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Check if two line segments cross or touch.
+fn segments_intersect(a: [[f64; 2]; 2], b: [[f64; 2]; 2]) -> bool {
+    let orientation = |p: [f64; 2], q: [f64; 2], r: [f64; 2]| {
+        let v = (q[0] - p[0]) * (r[1] - p[1]) - (q[1] - p[1]) * (r[0] - p[0]);
+        v.partial_cmp(&0.0).map_or(0, |o| o as i8)
+    };
+    let on_segment = |p: [f64; 2], q: [f64; 2], r: [f64; 2]| {
+        r[0] >= p[0].min(q[0]) && r[0] <= p[0].max(q[0])
+            && r[1] >= p[1].min(q[1]) && r[1] <= p[1].max(q[1])
+    };
+
+    let [p1, p2] = a;
+    let [q1, q2] = b;
+    let (o1, o2) = (orientation(p1, p2, q1), orientation(p1, p2, q2));
+    let (o3, o4) = (orientation(q1, q2, p1), orientation(q1, q2, p2));
+
+    (o1 != o2 && o3 != o4)
+        || (o1 == 0 && on_segment(p1, p2, q1))
+        || (o2 == 0 && on_segment(p1, p2, q2))
+        || (o3 == 0 && on_segment(q1, q2, p1))
+        || (o4 == 0 && on_segment(q1, q2, p2))
+}
+
+/// Every edge of a ring, including the one closing it.
+fn edges(ring: &[[f64; 2]]) -> impl Iterator<Item = [[f64; 2]; 2]> + '_ {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| [*a, *b])
+}
+
+fn to_point([lon, lat]: [f64; 2]) -> Point {
+    Point { lat, lon }
+}
+
+impl Geometry {
+    fn polygons(&self) -> &[Vec<Ring>] {
+        match self {
+            Geometry::Polygon(rings) => std::slice::from_ref(rings),
+            Geometry::MultiPolygon(polygons) => polygons,
+        }
+    }
+
+    /// Check if a point falls within the geometry.
+    pub fn contains(&self, point: Point) -> bool {
+        self.polygons().iter().any(|rings| match rings.split_first() {
+            Some((outline, holes)) => ring_contains(outline, point)
+                && !holes.iter().any(|hole| ring_contains(hole, point)),
+            None => false,
+        })
+    }
+
+    /// Check if two geometries overlap at all.
+    pub fn intersects(&self, other: &Geometry) -> bool {
+        let outlines = |g: &Geometry| -> Vec<Ring> {
+            g.polygons().iter().filter_map(|rings| rings.first().cloned()).collect()
+        };
+        let (ours, theirs) = (outlines(self), outlines(other));
+
+        // Either one is inside the other, or their outlines cross
+        ours.iter().flatten().any(|&p| other.contains(to_point(p)))
+            || theirs.iter().flatten().any(|&p| self.contains(to_point(p)))
+            || ours.iter().any(|a| theirs.iter().any(|b| {
+                edges(a).any(|e| edges(b).any(|f| segments_intersect(e, f)))
+            }))
+    }
+}
+
+impl AlertRoute {
+    /// Points earthquakes are measured from, either the watch's own or the route's.
+    pub fn earthquake_points(&self) -> &[Point] {
+        match &self.earthquakes {
+            Some(watch) if !watch.points.is_empty() => &watch.points,
+            _ => &self.points,
+        }
+    }
+
+    /// Points air quality is checked at, either the watch's own or the route's.
+    pub fn air_quality_points(&self) -> &[Point] {
+        match &self.air_quality {
+            Some(watch) if !watch.points.is_empty() => &watch.points,
+            _ => &self.points,
+        }
+    }
+
+    /// Check if an alert covers the places this route is watching.
+    /// `found_in` holds the areas, points, feeds and watches the alert was fetched for.
+    ///
+    /// Alerts without geometry apply to whole zones, so they go to every
+    /// route watching a zone they were found in. Alerts with geometry only go
+    /// to routes with points or a polygon if they overlap.
+    pub fn covers(&self, alert: &Alert, found_in: &HashSet<String>) -> bool {
+        let watched = self.areas.iter().any(|a| found_in.contains(a))
+            || self.points.iter().any(|p| found_in.contains(&p.to_string()))
+            || self.feeds.iter().any(|f| found_in.contains(&f.url))
+            || self.earthquakes.as_ref()
+                .is_some_and(|w| found_in.contains(&w.key(self.earthquake_points())))
+            || self.air_quality.as_ref()
+                .is_some_and(|w| found_in.contains(&w.key(self.air_quality_points())));
+        if !watched {
+            return false;
+        }
+
+        match &alert.geometry {
+            Some(geometry) if !self.points.is_empty() || self.polygon.is_some() => {
+                self.points.iter().any(|&p| geometry.contains(p))
+                    || self.polygon.as_ref().is_some_and(|area| area.intersects(geometry))
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A square with a square hole in the middle.
+    fn donut() -> Geometry {
+        Geometry::Polygon(vec![
+            vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]],
+            vec![[4.0, 4.0], [6.0, 4.0], [6.0, 6.0], [4.0, 6.0], [4.0, 4.0]],
+        ])
+    }
+
+    fn square(x: f64, y: f64, size: f64) -> Geometry {
+        Geometry::Polygon(vec![vec![
+            [x, y], [x + size, y], [x + size, y + size], [x, y + size], [x, y],
+        ]])
+    }
+
+    fn point(lon: f64, lat: f64) -> Point {
+        Point { lat, lon }
+    }
+
+    #[test]
+    fn distances_follow_the_earths_surface() {
+        let chico = point(-121.84, 39.73);
+        let sacramento = point(-121.49, 38.58);
+        assert!((chico.distance_km(sacramento) - 131.0).abs() < 2.0);
+        assert_eq!(chico.distance_km(chico), 0.0);
+    }
+
+    #[test]
+    fn points_in_holes_are_outside() {
+        let g = donut();
+        assert!(g.contains(point(2.0, 2.0)));
+        assert!(!g.contains(point(5.0, 5.0)));
+        assert!(!g.contains(point(11.0, 5.0)));
+    }
+
+    #[test]
+    fn multipolygons_contain_points_in_any_part() {
+        let g = Geometry::MultiPolygon(vec![
+            vec![vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]],
+            vec![vec![[5.0, 5.0], [6.0, 5.0], [6.0, 6.0], [5.0, 5.0]]],
+        ]);
+        assert!(g.contains(point(5.8, 5.2)));
+        assert!(!g.contains(point(3.0, 3.0)));
+    }
+
+    #[test]
+    fn overlapping_polygons_intersect() {
+        let a = square(0.0, 0.0, 2.0);
+        assert!(a.intersects(&square(1.0, 1.0, 2.0)));
+        // Entirely inside, with no crossing edges
+        assert!(a.intersects(&square(0.5, 0.5, 0.5)));
+        assert!(square(0.5, 0.5, 0.5).intersects(&a));
+        assert!(!a.intersects(&square(3.0, 3.0, 1.0)));
+    }
+
+    #[test]
+    fn crossing_polygons_intersect_without_shared_vertices() {
+        // A plus sign made of two thin rectangles
+        let wide = Geometry::Polygon(vec![vec![[0.0, 1.0], [3.0, 1.0], [3.0, 2.0], [0.0, 2.0], [0.0, 1.0]]]);
+        let tall = Geometry::Polygon(vec![vec![[1.0, 0.0], [2.0, 0.0], [2.0, 3.0], [1.0, 3.0], [1.0, 0.0]]]);
+        assert!(wide.intersects(&tall));
+    }
+}
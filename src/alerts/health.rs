@@ -0,0 +1,180 @@
+//! Tracks whether each alerts provider is reachable and backs off when it isn't.
+//!
+//! After a failed check, checks of that provider are skipped for an exponentially
+//! growing, jittered delay, or for as long as it asked with `Retry-After`.
+//! Other providers keep being checked as usual.
+
+use super::source::Throttled;
+use crate::{Error, config::AlertRoute};
+use chrono::{DateTime, TimeDelta, Utc};
+use rand::Rng;
+use reqwest::Url;
+use std::{fmt, time::Duration};
+
+/// Longest the poller backs off for between checks.
+const MAX_BACKOFF: Duration = Duration::from_mins(30);
+
+/// Consecutive failed checks before a provider counts as degraded.
+const DEGRADED_AFTER: u32 = 5;
+
+/// Somewhere alerts are fetched from, each with its own health.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Provider {
+    /// NWS, for US areas and points
+    Nws,
+    /// CAP feeds, by the host they are on (ex "feeds.meteoalarm.org")
+    Cap(String),
+    Usgs,
+    AirNow,
+}
+
+impl Provider {
+    /// Provider of a CAP feed, so feeds on the same host share health.
+    pub fn for_feed(url: &str) -> Self {
+        let host = Url::parse(url).ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_else(|| url.to_string());
+        Provider::Cap(host)
+    }
+
+    /// Check if a route gets alerts from this provider.
+    pub fn serves(&self, route: &AlertRoute) -> bool {
+        match self {
+            Provider::Nws => !route.areas.is_empty() || !route.points.is_empty(),
+            Provider::Cap(_) => route.feeds.iter().any(|f| Provider::for_feed(&f.url) == *self),
+            Provider::Usgs => route.earthquakes.is_some(),
+            Provider::AirNow => route.air_quality.is_some(),
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Provider::Nws => write!(f, "NWS"),
+            Provider::Cap(host) => write!(f, "{host}"),
+            Provider::Usgs => write!(f, "USGS"),
+            Provider::AirNow => write!(f, "AirNow"),
+        }
+    }
+}
+
+/// Health of one provider, as seen by the poller.
+#[derive(Clone, Debug, Default)]
+pub struct FeedHealth {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    /// Checks are skipped until this time while backing off
+    pub retry_at: Option<DateTime<Utc>>,
+    /// Whether a degraded notice has been sent for the current outage
+    pub degraded: bool,
+}
+
+/// How the health of a provider changed after a check.
+#[derive(Debug, Eq, PartialEq)]
+pub enum HealthChange {
+    Degraded,
+    Recovered,
+}
+
+/// Delay before the next check after `failures` failed checks in a row.
+/// Doubles with each failure, then a random amount of up to half is taken
+/// off so that restarts don't all retry at once.
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    let factor = 2u32.saturating_pow(failures.saturating_sub(1).min(16));
+    let delay = interval.saturating_mul(factor).min(MAX_BACKOFF);
+    let jitter = rand::rng().random_range(0.0..=0.5);
+    delay.mul_f64(1.0 - jitter)
+}
+
+impl FeedHealth {
+    /// Check if the poller should check the provider, or keep backing off.
+    pub fn should_poll(&self, now: DateTime<Utc>) -> bool {
+        self.retry_at.is_none_or(|retry_at| now >= retry_at)
+    }
+
+    /// Record a check where every request to the provider succeeded.
+    pub fn record_success(&mut self, now: DateTime<Utc>) -> Option<HealthChange> {
+        self.last_success = Some(now);
+        self.last_error = None;
+        self.consecutive_failures = 0;
+        self.retry_at = None;
+
+        std::mem::take(&mut self.degraded).then_some(HealthChange::Recovered)
+    }
+
+    /// Record a check where a request to the provider failed, and back off before the next one.
+    pub fn record_failure(&mut self, now: DateTime<Utc>, error: &Error, interval: Duration)
+    -> Option<HealthChange> {
+        self.last_error = Some(error.to_string());
+        self.consecutive_failures += 1;
+
+        let mut delay = backoff(interval, self.consecutive_failures);
+        if let Some(retry_after) = error.downcast_ref::<Throttled>().and_then(|t| t.retry_after) {
+            delay = delay.max(retry_after);
+        }
+        self.retry_at = TimeDelta::from_std(delay).ok().map(|d| now + d);
+
+        if self.consecutive_failures >= DEGRADED_AFTER && !self.degraded {
+            self.degraded = true;
+            return Some(HealthChange::Degraded);
+        }
+        None
+    }
+
+    /// Notice posted to the channels of routes using a provider when it becomes degraded or recovers.
+    pub fn notice(&self, provider: &Provider, change: HealthChange) -> String {
+        match change {
+            HealthChange::Degraded => {
+                let since = match self.last_success {
+                    Some(time) => format!("since <t:{}:R>", time.timestamp()),
+                    None => String::from("since the bot started"),
+                };
+                format!(
+                    "**Alerts feed from {provider} degraded.** Unable to fetch alerts {since} ({} failed checks). \
+                    Alerts from it may be delayed until it recovers.",
+                    self.consecutive_failures,
+                )
+            }
+            HealthChange::Recovered => {
+                format!("**Alerts feed from {provider} recovered.** Alerts are being checked as usual again.")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let interval = Duration::from_secs(60);
+        for failures in 1..=10 {
+            let full = interval.saturating_mul(2u32.pow(failures - 1)).min(MAX_BACKOFF);
+            let delay = backoff(interval, failures);
+            assert!(delay <= full && delay >= full / 2, "{failures} failures gave {delay:?}");
+        }
+    }
+
+    #[test]
+    fn feeds_on_the_same_host_share_a_provider() {
+        assert_eq!(
+            Provider::for_feed("https://feeds.meteoalarm.org/feeds/meteoalarm-legacy-atom-france"),
+            Provider::for_feed("https://feeds.meteoalarm.org/feeds/meteoalarm-legacy-atom-spain"),
+        );
+        assert_eq!(Provider::for_feed("https://alerts.example.org/cap.xml"), Provider::Cap("alerts.example.org".into()));
+    }
+
+    #[test]
+    fn retry_after_is_respected() {
+        let mut health = FeedHealth::default();
+        let now: DateTime<Utc> = "2026-10-17T12:00:00Z".parse().unwrap();
+        let error: Error = Box::new(Throttled { retry_after: Some(Duration::from_secs(600)) });
+
+        health.record_failure(now, &error, Duration::from_secs(60));
+        assert!(!health.should_poll(now + TimeDelta::seconds(599)));
+        assert!(health.should_poll(now + TimeDelta::seconds(600)));
+    }
+}
@@ -0,0 +1,201 @@
+//! Archive of every alert the bot has delivered.
+//!
+//! Seen alerts are dropped once they end, so delivered alerts are also
+//! archived in the db file, where they can be searched later. Alerts are
+//! kept for a year, up to [`MAX_HISTORY_LEN`] of the newest.
+
+use super::model::{Alert, MessageType, Severity};
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeDelta, Utc};
+use poise::serenity_prelude::{ChannelId, MessageId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// How long delivered alerts are kept in the archive
+const HISTORY_RETENTION: TimeDelta = TimeDelta::days(365);
+
+/// Most alerts kept in the archive, so the db file stays a reasonable size
+const MAX_HISTORY_LEN: usize = 10_000;
+
+/// A delivered alert, saved in the db file.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ArchivedAlert {
+    /// CAP identifier
+    pub id: String,
+    pub event: String,
+    pub message_type: MessageType,
+    pub severity: Severity,
+    pub area_desc: String,
+    /// NWS zone and county codes the alert covered
+    pub areas: Vec<String>,
+    pub sent: DateTime<FixedOffset>,
+    pub end: Option<DateTime<FixedOffset>>,
+    /// Messages the alert was posted as, including DMs
+    pub messages: Vec<(ChannelId, MessageId)>,
+}
+
+impl ArchivedAlert {
+    pub fn new(alert: &Alert, messages: &[(ChannelId, MessageId)]) -> Self {
+        Self {
+            id: alert.id.clone(),
+            event: alert.event.clone(),
+            message_type: alert.message_type,
+            severity: alert.severity,
+            area_desc: alert.area_desc.clone(),
+            areas: alert.geocode.ugc.clone(),
+            sent: alert.sent,
+            end: alert.end_time(),
+            messages: messages.to_vec(),
+        }
+    }
+}
+
+/// Add an alert to the archive, replacing its entry if it was archived already.
+pub fn archive(history: &mut Vec<ArchivedAlert>, entry: ArchivedAlert) {
+    match history.iter_mut().rev().find(|a| a.id == entry.id) {
+        Some(existing) => *existing = entry,
+        None => history.push(entry),
+    }
+}
+
+/// Drop alerts sent longer ago than the archive keeps them,
+/// then the oldest alerts if there are still too many.
+pub fn prune(history: &mut Vec<ArchivedAlert>, now: DateTime<Utc>) {
+    history.retain(|a| now - a.sent.to_utc() < HISTORY_RETENTION);
+    if history.len() > MAX_HISTORY_LEN {
+        history.sort_by_key(|a| a.sent);
+        history.drain(..history.len() - MAX_HISTORY_LEN);
+    }
+}
+
+/// What to search the archive for. Anything left out matches every alert.
+#[derive(Debug, Default)]
+pub struct HistoryQuery {
+    /// NWS zone or county code, ex "CAC007"
+    pub zone: Option<String>,
+    /// Part of the event name, ex "red flag"
+    pub event: Option<String>,
+    /// First day alerts were sent on, inclusive
+    pub from: Option<NaiveDate>,
+    /// Last day alerts were sent on, inclusive
+    pub to: Option<NaiveDate>,
+    /// Channels an alert must have been posted in, so alerts only DMed to
+    /// subscribers or posted in other servers aren't shown
+    pub channels: Option<HashSet<ChannelId>>,
+}
+
+impl HistoryQuery {
+    /// Check if an archived alert matches every part of the query.
+    pub fn matches(&self, alert: &ArchivedAlert) -> bool {
+        let sent = alert.sent.date_naive();
+        self.zone.as_ref().is_none_or(|zone| alert.areas.iter().any(|a| a.eq_ignore_ascii_case(zone)))
+            && self.event.as_ref().is_none_or(|event| {
+                alert.event.to_lowercase().contains(&event.to_lowercase())
+            })
+            && self.from.is_none_or(|from| sent >= from)
+            && self.to.is_none_or(|to| sent <= to)
+            && self.channels.as_ref().is_none_or(|channels| {
+                alert.messages.iter().any(|(c, _)| channels.contains(c))
+            })
+    }
+
+    /// Archived alerts matching the query, newest first.
+    pub fn search<'a>(&self, history: &'a [ArchivedAlert]) -> Vec<&'a ArchivedAlert> {
+        let mut found: Vec<&ArchivedAlert> = history.iter().filter(|a| self.matches(a)).collect();
+        found.sort_by_key(|a| std::cmp::Reverse(a.sent));
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn archived(id: &str, event: &str, areas: &[&str], sent: &str) -> ArchivedAlert {
+        ArchivedAlert {
+            id: id.to_string(),
+            event: event.to_string(),
+            message_type: MessageType::Alert,
+            severity: Severity::Severe,
+            area_desc: String::new(),
+            areas: areas.iter().map(|a| a.to_string()).collect(),
+            sent: DateTime::parse_from_rfc3339(sent).unwrap(),
+            end: None,
+            messages: Vec::new(),
+        }
+    }
+
+    fn history() -> Vec<ArchivedAlert> {
+        vec![
+            archived("a", "Red Flag Warning", &["CAZ215", "CAZ278"], "2026-10-01T03:00:00-07:00"),
+            archived("b", "Tornado Warning", &["CAC007"], "2026-10-05T15:00:00-07:00"),
+            archived("c", "Fire Weather Watch", &["CAZ215"], "2026-10-09T09:00:00-07:00"),
+        ]
+    }
+
+    fn ids(found: Vec<&ArchivedAlert>) -> Vec<&str> {
+        found.into_iter().map(|a| a.id.as_str()).collect()
+    }
+
+    #[test]
+    fn everything_matches_an_empty_query() {
+        let history = history();
+        assert_eq!(ids(HistoryQuery::default().search(&history)), ["c", "b", "a"]);
+    }
+
+    #[test]
+    fn queries_match_zone_event_and_dates() {
+        let history = history();
+        let zone = HistoryQuery { zone: Some("caz215".into()), ..Default::default() };
+        assert_eq!(ids(zone.search(&history)), ["c", "a"]);
+
+        let event = HistoryQuery { event: Some("WARNING".into()), ..Default::default() };
+        assert_eq!(ids(event.search(&history)), ["b", "a"]);
+
+        // Both ends of the range are included
+        let dates = HistoryQuery {
+            from: NaiveDate::from_ymd_opt(2026, 10, 1),
+            to: NaiveDate::from_ymd_opt(2026, 10, 5),
+            ..Default::default()
+        };
+        assert_eq!(ids(dates.search(&history)), ["b", "a"]);
+    }
+
+    #[test]
+    fn alerts_only_sent_elsewhere_are_hidden() {
+        let mut history = history();
+        history[0].messages.push((ChannelId::new(1), MessageId::new(10)));
+        // Only DMed to a subscriber
+        history[1].messages.push((ChannelId::new(1007), MessageId::new(11)));
+        // Posted in another server's channel
+        history[2].messages.push((ChannelId::new(2), MessageId::new(12)));
+
+        let query = HistoryQuery { channels: Some(HashSet::from([ChannelId::new(1)])), ..Default::default() };
+        assert_eq!(ids(query.search(&history)), ["a"]);
+    }
+
+    #[test]
+    fn old_alerts_are_pruned() {
+        let mut history = history();
+        prune(&mut history, "2027-10-03T12:00:00Z".parse().unwrap());
+        assert_eq!(ids(history.iter().collect()), ["b", "c"]);
+
+        let mut history: Vec<ArchivedAlert> = (0..MAX_HISTORY_LEN + 2)
+            .map(|i| archived(&i.to_string(), "Tornado Warning", &["CAC007"], "2026-10-05T15:00:00-07:00"))
+            .collect();
+        history[0].sent = DateTime::parse_from_rfc3339("2026-10-06T15:00:00-07:00").unwrap();
+        prune(&mut history, "2026-10-17T12:00:00Z".parse().unwrap());
+        assert_eq!(history.len(), MAX_HISTORY_LEN);
+        assert_eq!(history.last().unwrap().id, "0");
+    }
+
+    #[test]
+    fn archiving_again_replaces_the_entry() {
+        let mut history = history();
+        let mut entry = archived("b", "Tornado Warning", &["CAC007"], "2026-10-05T15:00:00-07:00");
+        entry.messages.push((ChannelId::new(1), MessageId::new(2)));
+
+        archive(&mut history, entry.clone());
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[1], entry);
+    }
+}
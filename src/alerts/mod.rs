@@ -0,0 +1,755 @@
+mod airnow;
+mod cap;
+mod checkin;
+mod digest;
+mod filter;
+mod fire;
+mod geo;
+mod health;
+mod history;
+mod model;
+mod nws;
+mod quiet;
+mod render;
+mod sink;
+mod source;
+mod subscription;
+#[cfg(test)]
+mod tests;
+mod usgs;
+
+use crate::{
+    Error,
+    config::{AirQualityWatch, AlertRoute, CapFeed, Config, EarthquakeWatch},
+    db::Database,
+};
+use chrono::{DateTime, Utc};
+use airnow::AirNowSource;
+use cap::CapSource;
+use health::HealthChange;
+use model::Alert;
+use poise::serenity_prelude::{ChannelId, Http, MessageId, UserId};
+use quiet::schedule_for;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sink::AlertSink;
+use subscription::subscribers_for;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc, time::Duration,
+};
+use source::Throttled;
+use tokio::sync::{Mutex, watch::{Receiver, Sender}};
+use tracing::{info, error, warn};
+use usgs::UsgsSource;
+
+pub use checkin::{CheckIns, collect_check_ins};
+pub use digest::PendingDigest;
+pub use geo::{Geometry, Point};
+pub use health::{FeedHealth, Provider};
+pub use history::{ArchivedAlert, HistoryQuery};
+pub use model::{Category, Certainty, MessageType, Severity, Urgency};
+pub use nws::{NwsSource, is_zone_code};
+pub use quiet::QuietQueue;
+pub use render::{AlertStatus, alert_embed, forecast_embed};
+pub use source::{APP_USER_AGENT, AlertSource};
+pub use subscription::{Subscription, can_subscribe};
+
+/// An alert that has already been handled, along with
+/// the Discord messages it was posted as in each channel and DM.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Posted {
+    alert: Alert,
+    #[serde(default)]
+    messages: Vec<(ChannelId, MessageId)>,
+    /// Subscribers the alert was sent to by DM
+    #[serde(default)]
+    users: HashSet<UserId>,
+    /// Digest messages the alert was included in, for routes in digest mode
+    #[serde(default)]
+    digests: Vec<(ChannelId, MessageId)>,
+}
+
+impl Posted {
+    /// Every message the alert went out in, to link to from the archive.
+    fn delivered(&self) -> Vec<(ChannelId, MessageId)> {
+        self.messages.iter().chain(&self.digests).copied().collect()
+    }
+}
+
+/// Everything the alerts task keeps track of between polls.
+#[derive(Debug, Default)]
+struct AlertState {
+    /// Already seen alerts, keyed by CAP identifier
+    alert_list: HashMap<String, Posted>,
+    /// New alerts held back during quiet hours
+    queue: QuietQueue,
+    /// Health of each provider in the config
+    health: BTreeMap<Provider, FeedHealth>,
+    /// Alerts delivered since the db file was last saved
+    archived: Vec<ArchivedAlert>,
+    /// Alerts waiting for the next digest of each route in digest mode
+    digests: HashMap<ChannelId, PendingDigest>,
+}
+
+/// Write the seen alerts, pending digests and held alerts to the db file so
+/// they survive a restart, and add newly delivered alerts to the archive, dropping old ones
+/// along with their check-ins.
+async fn save_alerts(state: &mut AlertState, db: &Mutex<Database>, db_path: &str) {
+    let mut db = db.lock().await;
+    db.alerts = state.alert_list.clone();
+    db.digests = state.digests.clone();
+    db.quiet_queue = state.queue.clone();
+    for entry in state.archived.drain(..) {
+        history::archive(&mut db.history, entry);
+    }
+    history::prune(&mut db.history, Utc::now());
+    let db = &mut *db;
+    checkin::prune(&mut db.check_ins, &db.history);
+    if let Err(e) = db.save(db_path).await {
+        warn!("Failed to save seen alerts to db file: {e}");
+    }
+}
+
+/// Post an alert to every route that doesn't have a message for it yet.
+async fn post_to_routes(
+    alert: &Alert,
+    routes: &[&AlertRoute],
+    messages: &mut Vec<(ChannelId, MessageId)>,
+    sink: &impl AlertSink,
+) {
+    for route in routes {
+        let channel_id = ChannelId::new(route.channel);
+        if messages.iter().any(|(c, _)| *c == channel_id) {
+            continue;
+        }
+        match sink.post(alert, route).await {
+            Ok(message_id) => messages.push((channel_id, message_id)),
+            Err(e) => error!("Failed to send alert {} to channel {channel_id}: {e}", alert.id),
+        }
+    }
+}
+
+/// DM an alert to every subscriber that hasn't been sent it yet.
+async fn dm_users(
+    alert: &Alert,
+    users: &[UserId],
+    sent_to: &mut HashSet<UserId>,
+    messages: &mut Vec<(ChannelId, MessageId)>,
+    sink: &impl AlertSink,
+) {
+    for &user_id in users {
+        // Recorded even if the DM fails, as members with DMs closed
+        // would otherwise be retried on every poll
+        if !sent_to.insert(user_id) {
+            continue;
+        }
+        match sink.dm(alert, user_id).await {
+            Ok(message) => messages.push(message),
+            Err(e) => warn!("Failed to DM alert {} to user {user_id}: {e}", alert.id),
+        }
+    }
+}
+
+/// Summary sent before alerts held back during quiet hours.
+fn digest_notice(count: usize) -> String {
+    let plural = if count == 1 { "" } else { "s" };
+    format!("**Quiet hours have ended.** {count} alert{plural} came in while they were on:")
+}
+
+/// Where the poller gets alerts from.
+enum Request<'a> {
+    Area(&'a str),
+    Point(Point),
+    Feed(&'a CapFeed),
+    Earthquakes(&'a EarthquakeWatch, &'a [Point]),
+    AirQuality(&'a AirQualityWatch, &'a [Point]),
+}
+
+impl Request<'_> {
+    fn provider(&self) -> Provider {
+        match self {
+            Request::Area(_) | Request::Point(_) => Provider::Nws,
+            Request::Feed(feed) => Provider::for_feed(&feed.url),
+            Request::Earthquakes(..) => Provider::Usgs,
+            Request::AirQuality(..) => Provider::AirNow,
+        }
+    }
+}
+
+/// Every request needed for the areas, points, feeds and watches, keyed by
+/// the area, point (formatted as "lat,lon"), feed URL or watch key.
+fn requests<'a>(
+    areas: HashSet<&'a String>,
+    points: Vec<Point>,
+    feeds: Vec<&'a CapFeed>,
+    earthquakes: Vec<(&'a EarthquakeWatch, &'a [Point])>,
+    air_quality: Vec<(&'a AirQualityWatch, &'a [Point])>,
+) -> Vec<(String, Request<'a>)> {
+    areas.into_iter()
+        .map(|area| (area.clone(), Request::Area(area)))
+        .chain(points.into_iter().map(|point| (point.to_string(), Request::Point(point))))
+        .chain(feeds.into_iter().map(|feed| (feed.url.clone(), Request::Feed(feed))))
+        .chain(earthquakes.into_iter().map(|(watch, points)| {
+            (watch.key(points), Request::Earthquakes(watch, points))
+        }))
+        .chain(air_quality.into_iter().map(|(watch, points)| {
+            (watch.key(points), Request::AirQuality(watch, points))
+        }))
+        .collect()
+}
+
+/// Make every request to a provider that `should_poll` allows. Each alert is returned
+/// with the keys of the requests it was found in, oldest alert first, along with each
+/// provider that was checked and its last error if any of its requests failed.
+async fn poll_areas(
+    source: &impl AlertSource,
+    requests: Vec<(String, Request<'_>)>,
+    should_poll: impl Fn(&Provider) -> bool,
+) -> (Vec<(Alert, HashSet<String>)>, BTreeMap<Provider, Option<Error>>) {
+    let mut found: HashMap<String, (Alert, HashSet<String>)> = HashMap::new();
+    let mut add = |alerts: Vec<Alert>, key: String| {
+        for alert in alerts {
+            found.entry(alert.id.clone())
+                .or_insert_with(|| (alert, HashSet::new()))
+                .1
+                .insert(key.clone());
+        }
+    };
+
+    // Handle any errors without terminating the task
+    let mut checked: BTreeMap<Provider, Option<Error>> = BTreeMap::new();
+    for (key, request) in requests {
+        let provider = request.provider();
+        // Don't keep making requests to a provider once asked to slow down
+        let throttled = checked.get(&provider)
+            .is_some_and(|e| e.as_ref().is_some_and(|e| e.is::<Throttled>()));
+        if throttled || !should_poll(&provider) {
+            continue;
+        }
+
+        let result = match request {
+            Request::Area(area) => source.fetch_active(area).await,
+            Request::Point(point) => source.fetch_active_at(point).await,
+            Request::Feed(feed) => source.fetch_feed(feed).await,
+            Request::Earthquakes(watch, points) => source.fetch_earthquakes(watch, points).await,
+            Request::AirQuality(watch, points) => source.fetch_air_quality(watch, points).await,
+        };
+        let last_error = checked.entry(provider).or_default();
+        match result {
+            Ok(alerts) => add(alerts, key),
+            Err(e) => {
+                error!("Failed to fetch alerts for {key}: {e:#}");
+                *last_error = Some(e);
+            }
+        }
+    }
+
+    // Handle alerts in the order they were sent so updates follow the originals
+    let mut found: Vec<_> = found.into_values().collect();
+    found.sort_by_key(|(alert, _)| alert.sent);
+    (found, checked)
+}
+
+impl AlertState {
+    /// Removes alerts that are past their specified end time.
+    fn clear_ended_alerts(&mut self, now: DateTime<Utc>) {
+        self.alert_list.retain(|_, p| p.alert.end_time().is_some_and(|end| end > now));
+    }
+
+    /// Check if an alert, or an earlier version it references, was already posted to a channel.
+    fn posted_in(&self, alert: &Alert, channel_id: ChannelId) -> bool {
+        std::iter::once(&alert.id)
+            .chain(alert.references.iter().map(|r| &r.identifier))
+            .filter_map(|id| self.alert_list.get(id))
+            .any(|p| p.messages.iter().any(|(c, _)| *c == channel_id))
+    }
+
+    /// Check if an alert, or an earlier version it references, was already sent to a subscriber.
+    fn sent_to(&self, alert: &Alert, user_id: UserId) -> bool {
+        std::iter::once(&alert.id)
+            .chain(alert.references.iter().map(|r| &r.identifier))
+            .filter_map(|id| self.alert_list.get(id))
+            .any(|p| p.users.contains(&user_id))
+    }
+
+    /// Check if this exact version of an alert was already included in a channel's digest.
+    /// Updates go in the next digest even if the original was in an earlier one.
+    fn digested_in(&self, alert: &Alert, channel_id: ChannelId) -> bool {
+        self.alert_list.get(&alert.id)
+            .is_some_and(|p| p.digests.iter().any(|(c, _)| *c == channel_id))
+    }
+
+    /// Post, update or cancel an alert depending on its message type
+    /// and whether any alert it references has already been posted.
+    /// Returns whether the list of seen alerts changed.
+    async fn handle_alert(
+        &mut self,
+        alert: Alert,
+        routes: &[&AlertRoute],
+        users: &[UserId],
+        sink: &impl AlertSink,
+    ) -> bool {
+        if let Some(posted) = self.alert_list.get_mut(&alert.id) {
+            // Already handled, but routes and subscribers held back by quiet hours may still need it
+            if alert.message_type == MessageType::Cancel {
+                return false;
+            }
+            let old_len = (posted.messages.len(), posted.users.len());
+            post_to_routes(&alert, routes, &mut posted.messages, sink).await;
+            dm_users(&alert, users, &mut posted.users, &mut posted.messages, sink).await;
+            if posted.messages.len() != old_len.0 {
+                history::archive(&mut self.archived, ArchivedAlert::new(&posted.alert, &posted.delivered()));
+            }
+            return (posted.messages.len(), posted.users.len()) != old_len;
+        }
+
+        // Follow the reference chain back to the most recent posted version
+        let previous = alert.references.iter()
+            .rev()
+            .find_map(|r| self.alert_list.get(&r.identifier))
+            .cloned();
+        let mut messages = previous.as_ref()
+            .map(|p| p.messages.clone())
+            .unwrap_or_default();
+        let mut sent_to = previous.as_ref()
+            .map(|p| p.users.clone())
+            .unwrap_or_default();
+
+        let shown = match (alert.message_type, previous) {
+            (MessageType::Cancel, Some(posted)) => {
+                for &message in &messages {
+                    if let Err(e) = sink.edit(&posted.alert, AlertStatus::Cancelled, message).await {
+                        error!("Failed to cancel alert {} in channel {}: {e}", alert.id, message.0);
+                    }
+                }
+                // The messages still show the original alert, now marked as cancelled
+                posted.alert
+            }
+            // Nothing was posted for this alert, so there is nothing to cancel
+            (MessageType::Cancel, None) => alert.clone(),
+            (_, previous) => {
+                if previous.is_some() {
+                    for &message in &messages {
+                        if let Err(e) = sink.edit(&alert, AlertStatus::Updated, message).await {
+                            error!("Failed to update alert {} in channel {}: {e}", alert.id, message.0);
+                        }
+                    }
+                }
+
+                // Post to any route or subscriber that hasn't seen an earlier version,
+                // channels first
+                post_to_routes(&alert, routes, &mut messages, sink).await;
+                dm_users(&alert, users, &mut sent_to, &mut messages, sink).await;
+                alert.clone()
+            }
+        };
+
+        if !messages.is_empty() {
+            history::archive(&mut self.archived, ArchivedAlert::new(&alert, &messages));
+        }
+
+        // Keep the end time of the newest message so cleanup follows it
+        let shown = Alert { ends: alert.ends, expires: alert.expires, ..shown };
+        self.alert_list.insert(alert.id, Posted { alert: shown, messages, users: sent_to, digests: Vec::new() });
+        true
+    }
+
+    /// Let the channel of every route using a provider know it became degraded or recovered.
+    async fn announce_health(
+        &self,
+        provider: &Provider,
+        change: HealthChange,
+        routes: &[AlertRoute],
+        sink: &impl AlertSink,
+    ) {
+        let text = self.health.get(provider).cloned().unwrap_or_default().notice(provider, change);
+        warn!("{text}");
+
+        let channels: HashSet<ChannelId> = routes.iter()
+            .filter(|r| provider.serves(r))
+            .map(|r| ChannelId::new(r.channel))
+            .collect();
+        for channel_id in channels {
+            if let Err(e) = sink.notice(channel_id, &text).await {
+                error!("Failed to send feed health notice to channel {channel_id}: {e}");
+            }
+        }
+    }
+
+    /// Deliver the alerts held back for routes and subscribers whose quiet hours
+    /// have ended, introduced by a short summary wherever they are going to.
+    /// Alerts held for routes that have since been removed are dropped.
+    /// Returns whether the seen alerts or held alerts changed.
+    async fn deliver_digest(
+        &mut self,
+        routes: &[AlertRoute],
+        is_quiet: impl Fn(ChannelId) -> bool,
+        is_user_quiet: impl Fn(UserId) -> bool,
+        sink: &impl AlertSink,
+    ) -> bool {
+        let ready = self.queue.take_ready(is_quiet, is_user_quiet);
+        let route_for = |channel_id: &ChannelId| routes.iter().find(|r| r.channel == channel_id.get());
+
+        let mut counts: HashMap<ChannelId, usize> = HashMap::new();
+        let mut user_counts: HashMap<UserId, usize> = HashMap::new();
+        for q in &ready {
+            for &channel_id in q.channels.iter().filter(|c| route_for(c).is_some()) {
+                *counts.entry(channel_id).or_default() += 1;
+            }
+            for &user_id in &q.users {
+                *user_counts.entry(user_id).or_default() += 1;
+            }
+        }
+        for (channel_id, count) in counts {
+            if let Err(e) = sink.notice(channel_id, &digest_notice(count)).await {
+                error!("Failed to send quiet hours digest to channel {channel_id}: {e}");
+            }
+        }
+        for (user_id, count) in user_counts {
+            if let Err(e) = sink.dm_notice(user_id, &digest_notice(count)).await {
+                warn!("Failed to DM quiet hours digest to user {user_id}: {e}");
+            }
+        }
+
+        let mut changed = !ready.is_empty();
+        for q in ready {
+            let routes: Vec<&AlertRoute> = q.channels.iter().filter_map(route_for).collect();
+            changed |= self.handle_alert(q.alert, &routes, &q.users, sink).await;
+        }
+        changed
+    }
+
+    /// Post the digest of every route in digest mode that is due one.
+    /// Digests with no alerts are skipped, but still count as posted.
+    /// Returns whether the seen alerts or pending digests changed.
+    async fn post_scheduled_digests(
+        &mut self,
+        routes: &[AlertRoute],
+        now: DateTime<Utc>,
+        sink: &impl AlertSink,
+    ) -> bool {
+        // Forget digests of routes that were removed or left digest mode
+        let old_len = self.digests.len();
+        self.digests.retain(|c, _| routes.iter().any(|r| r.digest.is_some() && r.channel == c.get()));
+        let mut changed = self.digests.len() != old_len;
+
+        for route in routes {
+            let Some(schedule) = &route.digest else {
+                continue;
+            };
+            let channel_id = ChannelId::new(route.channel);
+            let pending = self.digests.entry(channel_id).or_insert_with(|| {
+                changed = true;
+                PendingDigest::new(now)
+            });
+            if schedule.next_after(pending.since).is_none_or(|due| due > now) {
+                continue;
+            }
+
+            changed = true;
+            let since = std::mem::replace(&mut pending.since, now);
+            let alerts = std::mem::take(&mut pending.alerts);
+            if alerts.is_empty() {
+                continue;
+            }
+
+            match sink.digest(&alerts, since, route).await {
+                Ok(message_id) => {
+                    for alert in alerts {
+                        let posted = self.alert_list.entry(alert.id.clone()).or_insert_with(|| Posted {
+                            alert,
+                            messages: Vec::new(),
+                            users: HashSet::new(),
+                            digests: Vec::new(),
+                        });
+                        posted.digests.push((channel_id, message_id));
+                        history::archive(&mut self.archived, ArchivedAlert::new(&posted.alert, &posted.delivered()));
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to post digest to channel {channel_id}: {e}");
+                    // Kept so the digest is tried again on the next poll
+                    *pending = PendingDigest { since, alerts };
+                }
+            }
+        }
+        changed
+    }
+
+    /// Deliver alerts held back by quiet hours, fetch the active alerts and
+    /// deliver them, then post any digests that are due.
+    /// Returns whether the seen alerts, held alerts or pending digests changed.
+    async fn poll(
+        &mut self,
+        cfg: &Config,
+        subscriptions: &HashMap<UserId, Subscription>,
+        now: DateTime<Utc>,
+        source: &impl AlertSource,
+        sink: &impl AlertSink,
+    ) -> bool {
+        let routes = &cfg.alerts.routes;
+        let quiet_hours = cfg.quiet_hours.as_ref();
+        let is_quiet = |channel_id: ChannelId| routes.iter()
+            .find(|r| r.channel == channel_id.get())
+            .and_then(|r| schedule_for(r, quiet_hours))
+            .is_some_and(|q| q.is_quiet(now));
+        let is_user_quiet = |user_id: UserId| subscriptions.get(&user_id)
+            .and_then(|s| s.quiet_hours.as_ref())
+            .is_some_and(|q| q.is_quiet(now));
+
+        let mut changed = self.deliver_digest(routes, is_quiet, is_user_quiet, sink).await;
+        changed |= self.fetch_and_deliver(cfg, subscriptions, now, source, sink).await;
+        // After fetching, so alerts that just came in make it into a digest that is due
+        changed |= self.post_scheduled_digests(&cfg.alerts.routes, now, sink).await;
+        changed
+    }
+
+    /// Fetch the active alerts from every provider that isn't backing off, filter
+    /// them and deliver them to the matching routes, then to any subscribers of
+    /// the alert's areas. Alerts for routes in digest mode are collected for
+    /// their next digest instead.
+    /// Returns whether the seen alerts, held alerts or pending digests changed.
+    async fn fetch_and_deliver(
+        &mut self,
+        cfg: &Config,
+        subscriptions: &HashMap<UserId, Subscription>,
+        now: DateTime<Utc>,
+        source: &impl AlertSource,
+        sink: &impl AlertSink,
+    ) -> bool {
+        let routes = &cfg.alerts.routes;
+        let quiet_hours = cfg.quiet_hours.as_ref();
+        // Subscribers only have the quiet hours they set themselves
+        let user_quiet_hours = |user_id: UserId| subscriptions.get(&user_id)
+            .and_then(|s| s.quiet_hours.as_ref());
+        let mut changed = false;
+
+        let areas: HashSet<&String> = routes.iter()
+            .flat_map(|r| &r.areas)
+            .chain(subscriptions.values().flat_map(|s| &s.zones))
+            .collect();
+
+        // Points are compared by how they are formatted for the API
+        let points: HashMap<String, Point> = routes.iter()
+            .flat_map(|r| r.points.iter().map(|p| (p.to_string(), *p)))
+            .collect();
+        let points = points.into_values().collect();
+
+        let feeds: HashMap<&str, &CapFeed> = routes.iter()
+            .flat_map(|r| r.feeds.iter().map(|f| (f.url.as_str(), f)))
+            .collect();
+        let feeds = feeds.into_values().collect();
+
+        // Watches are compared by their keys, as routes can share one
+        let earthquakes: HashMap<String, (&EarthquakeWatch, &[Point])> = routes.iter()
+            .filter_map(|r| {
+                let watch = r.earthquakes.as_ref()?;
+                let points = r.earthquake_points();
+                Some((watch.key(points), (watch, points)))
+            })
+            .collect();
+        let earthquakes = earthquakes.into_values().collect();
+        // Skipped rather than failing every check, as the AirNow API key is optional
+        let air_quality: HashMap<String, (&AirQualityWatch, &[Point])> = routes.iter()
+            .filter_map(|r| {
+                let watch = r.air_quality.as_ref().filter(|_| source.checks_air_quality())?;
+                let points = r.air_quality_points();
+                Some((watch.key(points), (watch, points)))
+            })
+            .collect();
+        let air_quality = air_quality.into_values().collect();
+
+        let requests = requests(areas, points, feeds, earthquakes, air_quality);
+        // Forget providers that are no longer in the config
+        let providers: HashSet<Provider> = requests.iter().map(|(_, r)| r.provider()).collect();
+        self.health.retain(|p, _| providers.contains(p));
+
+        let health = &self.health;
+        let should_poll = |p: &Provider| health.get(p).is_none_or(|h| h.should_poll(now));
+        let (found, checked) = poll_areas(source, requests, should_poll).await;
+        for (provider, error) in checked {
+            let health = self.health.entry(provider.clone()).or_default();
+            let health_change = match error {
+                Some(e) => health.record_failure(now, &e, cfg.alerts.check_interval),
+                None => health.record_success(now),
+            };
+            if let Some(change) = health_change {
+                self.announce_health(&provider, change, routes, sink).await;
+            }
+        }
+
+        for (alert, areas) in found {
+            if alert.end_time().is_none() {
+                error!("Alert missing 'ends' and 'expires' time, ID: {}", alert.id);
+                continue;
+            }
+
+            if !cfg.alerts.filter.matches(&alert) {
+                continue;
+            }
+
+            // Routes covered by the alert that want this kind of alert
+            let (digest, matching): (Vec<&AlertRoute>, Vec<&AlertRoute>) = routes.iter()
+                .filter(|r| r.covers(&alert, &areas))
+                .filter(|r| r.filter.matches(&alert))
+                .partition(|r| r.digest.is_some());
+            for route in digest {
+                let channel_id = ChannelId::new(route.channel);
+                if !self.digested_in(&alert, channel_id) {
+                    changed |= self.digests.entry(channel_id)
+                        .or_insert_with(|| PendingDigest::new(now))
+                        .push(alert.clone());
+                }
+            }
+
+            // Edits to already posted alerts don't notify anyone, so only new
+            // posts are held back, unless the alert is severe enough to override
+            let (held, live): (Vec<&AlertRoute>, Vec<&AlertRoute>) = matching.into_iter().partition(|r| {
+                schedule_for(r, quiet_hours)
+                    .is_some_and(|q| q.is_quiet(now) && !q.overridden_by(alert.severity))
+                    && !self.posted_in(&alert, ChannelId::new(r.channel))
+            });
+            let (held_users, live_users): (Vec<UserId>, Vec<UserId>) = subscribers_for(subscriptions, &areas)
+                .into_iter()
+                .partition(|&u| {
+                    user_quiet_hours(u)
+                        .is_some_and(|q| q.is_quiet(now) && !q.overridden_by(alert.severity))
+                        && !self.sent_to(&alert, u)
+                });
+            if held.is_empty() && live.is_empty() && held_users.is_empty() && live_users.is_empty() {
+                continue;
+            }
+
+            if !held.is_empty() || !held_users.is_empty() {
+                let channels = held.iter().map(|r| ChannelId::new(r.channel)).collect();
+                self.queue.push(alert.clone(), channels, held_users);
+                changed = true;
+            }
+            changed |= self.handle_alert(alert, &live, &live_users, sink).await;
+        }
+
+        changed
+    }
+}
+
+/// Every provider alerts can come from, NWS for US areas and points,
+/// CAP feeds for everywhere else, USGS for earthquakes and AirNow for air quality.
+struct Providers {
+    nws: NwsSource,
+    cap: CapSource,
+    usgs: UsgsSource,
+    airnow: AirNowSource,
+}
+
+impl AlertSource for Providers {
+    async fn fetch_active(&self, area: &str) -> Result<Vec<Alert>, Error> {
+        self.nws.fetch_active(area).await
+    }
+
+    async fn fetch_active_at(&self, point: Point) -> Result<Vec<Alert>, Error> {
+        self.nws.fetch_active_at(point).await
+    }
+
+    async fn fetch_feed(&self, feed: &CapFeed) -> Result<Vec<Alert>, Error> {
+        self.cap.fetch_feed(feed).await
+    }
+
+    async fn fetch_earthquakes(&self, watch: &EarthquakeWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        self.usgs.fetch_earthquakes(watch, points).await
+    }
+
+    fn checks_air_quality(&self) -> bool {
+        self.airnow.has_key()
+    }
+
+    async fn fetch_air_quality(&self, watch: &AirQualityWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        self.airnow.fetch_air_quality(watch, points).await
+    }
+}
+
+/// AirNow API key, kept out of the config file as it is a secret.
+/// Warns if any route watches air quality without one, as it is skipped.
+fn airnow_key(cfg: &Config) -> Option<String> {
+    let key = std::env::var("AIRNOW_API_KEY").ok().filter(|k| !k.is_empty());
+    if key.is_none() && cfg.alerts.routes.iter().any(|r| r.air_quality.is_some()) {
+        warn!("AIRNOW_API_KEY isn't set, so air quality isn't being checked");
+    }
+    key
+}
+
+/// Runs in the background of the bot if configured.
+/// Pulls weather alerts from the nws.gov API and any CAP feeds,
+/// earthquakes from USGS and air quality from AirNow, and sends
+/// them to the channels of the configured routes and to
+/// subscribed members by DM.
+pub async fn alerts(
+    http: Arc<Http>,
+    cfg: Config,
+    mut rx: Receiver<Config>,
+    db: Arc<Mutex<Database>>,
+    db_path: String,
+    client: Client,
+    health_tx: Sender<BTreeMap<Provider, FeedHealth>>,
+) -> Result<(), Error> {
+    let mut state = {
+        let db = db.lock().await;
+        AlertState {
+            alert_list: db.alerts.clone(),
+            digests: db.digests.clone(),
+            queue: db.quiet_queue.clone(),
+            ..Default::default()
+        }
+    };
+    info!("Loaded {} previously seen alerts", state.alert_list.len());
+
+    let mut source = Providers {
+        nws: NwsSource::new(client.clone(), &cfg.alerts.nws_url),
+        cap: CapSource::new(client.clone()),
+        usgs: UsgsSource::new(client.clone(), &cfg.alerts.usgs_url),
+        airnow: AirNowSource::new(client.clone(), &cfg.alerts.airnow_url, airnow_key(&cfg)),
+    };
+    let mut cfg = cfg;
+
+    let mut check_interval = tokio::time::interval(cfg.alerts.check_interval);
+
+    // Check for ended alerts every 24 hours
+    let mut cleanup_interval = tokio::time::interval(Duration::from_hours(24));
+
+    info!("Listening for NWS alerts");
+
+    loop {
+        tokio::select! {
+            _ = check_interval.tick() => {
+                let subscriptions = db.lock().await.subscriptions.clone();
+                if state.poll(&cfg, &subscriptions, Utc::now(), &source, &http).await {
+                    save_alerts(&mut state, &db, &db_path).await;
+                }
+                health_tx.send_replace(state.health.clone());
+            }
+            _ = cleanup_interval.tick() => {
+                let old_len = state.alert_list.len();
+                state.clear_ended_alerts(Utc::now());
+                info!("Cleared {} stale alerts", old_len - state.alert_list.len());
+                if state.alert_list.len() != old_len {
+                    save_alerts(&mut state, &db, &db_path).await;
+                }
+            }
+            // Reload config when signalled
+            _ = rx.changed() => {
+                info!("Config updated, applying new settings");
+                cfg = rx.borrow_and_update().clone();
+                #[cfg(debug_assertions)]
+                println!("New config: {:?}", cfg);
+
+                // Update config values
+                check_interval = tokio::time::interval(cfg.alerts.check_interval);
+                source.nws = NwsSource::new(client.clone(), &cfg.alerts.nws_url);
+                source.usgs = UsgsSource::new(client.clone(), &cfg.alerts.usgs_url);
+                source.airnow.reconfigure(&cfg.alerts.airnow_url, airnow_key(&cfg));
+            }
+        }
+    }
+}
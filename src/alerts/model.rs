@@ -0,0 +1,147 @@
+//! Typed model of an NWS alert.
+//!
+//! Field names follow the `properties` object of the GeoJSON
+//! features returned by `api.weather.gov/alerts`, which in turn
+//! follow the Common Alerting Protocol (CAP) 1.2 spec:
+//! https://docs.oasis-open.org/emergency/cap/v1.2/CAP-v1.2.html
+
+use super::geo::Geometry;
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use serde_with::{DefaultOnNull, serde_as};
+use std::fmt::{self, Display};
+
+/// CAP `category` of an alert.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, Hash, PartialEq)]
+pub enum Category {
+    Geo,
+    Met,
+    Safety,
+    Security,
+    Rescue,
+    Fire,
+    Health,
+    Env,
+    Transport,
+    Infra,
+    #[serde(rename = "CBRNE")]
+    Cbrne,
+    Other,
+}
+
+/// CAP `severity` of an alert, ordered from least to most severe.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    #[default]
+    Unknown,
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+/// CAP `urgency` of an alert, ordered from least to most urgent.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Urgency {
+    #[default]
+    Unknown,
+    Past,
+    Future,
+    Expected,
+    Immediate,
+}
+
+/// CAP `certainty` of an alert, ordered from least to most certain.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Certainty {
+    #[default]
+    Unknown,
+    Unlikely,
+    Possible,
+    Likely,
+    Observed,
+}
+
+/// CAP `msgType` of an alert.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, Hash, PartialEq)]
+pub enum MessageType {
+    Alert,
+    Update,
+    Cancel,
+    Ack,
+    Error,
+}
+
+/// A previous alert that this one updates or cancels.
+#[derive(Clone, Debug, Deserialize, Serialize, Eq, Hash, PartialEq)]
+pub struct Reference {
+    /// URL of the referenced alert
+    #[serde(rename = "@id")]
+    pub url: String,
+    /// CAP identifier of the referenced alert
+    pub identifier: String,
+    pub sender: String,
+    pub sent: DateTime<FixedOffset>,
+}
+
+/// Codes for the areas an alert covers.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, Hash, PartialEq)]
+pub struct Geocode {
+    /// NWS zone and county codes, ex "CAZ215"
+    #[serde(rename = "UGC", default)]
+    pub ugc: Vec<String>,
+}
+
+/// A single weather alert.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    /// CAP identifier, unique per message
+    pub id: String,
+    pub area_desc: String,
+    #[serde(default)]
+    pub geocode: Geocode,
+    pub sent: DateTime<FixedOffset>,
+    pub effective: DateTime<FixedOffset>,
+    pub onset: Option<DateTime<FixedOffset>>,
+    pub expires: Option<DateTime<FixedOffset>>,
+    pub ends: Option<DateTime<FixedOffset>>,
+    pub message_type: MessageType,
+    pub category: Category,
+    pub severity: Severity,
+    pub certainty: Certainty,
+    pub urgency: Urgency,
+    pub event: String,
+    pub sender_name: String,
+    pub headline: Option<String>,
+    #[serde_as(as = "DefaultOnNull")]
+    #[serde(default)]
+    pub description: String,
+    pub instruction: Option<String>,
+    #[serde_as(as = "DefaultOnNull")]
+    #[serde(default)]
+    pub references: Vec<Reference>,
+    /// Web page for the alert, set for alerts from CAP feeds
+    #[serde(default)]
+    pub link: Option<String>,
+    /// Area the alert covers, if it is polygon based. Taken from the
+    /// enclosing GeoJSON feature and not saved with seen alerts.
+    #[serde(skip)]
+    pub geometry: Option<Geometry>,
+}
+
+impl Alert {
+    /// Time the alert stops being relevant.
+    /// Prefers the end of the hazard and falls back on the expiry of the message.
+    pub fn end_time(&self) -> Option<DateTime<FixedOffset>> {
+        self.ends.or(self.expires)
+    }
+}
+
+impl Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headline = self.headline.as_deref().unwrap_or(&self.event);
+        write!(f, "Category: {:?}\n{}\n{}", self.category, headline, self.description)
+    }
+}
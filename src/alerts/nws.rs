@@ -0,0 +1,278 @@
+//! GeoJSON wrappers around the responses of the NWS API.
+//!
+//! https://www.weather.gov/documentation/services-web-api
+
+use super::{
+    geo::{Geometry, Point},
+    model::Alert,
+    source::{APP_USER_AGENT, AlertSource, ResponseCache, check_status},
+};
+use crate::Error;
+use reqwest::{Client, header::USER_AGENT};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use serde_with::{DefaultOnError, serde_as};
+use std::collections::HashMap;
+use tracing::error;
+
+/// States and territories that have NWS zones and counties.
+const STATES: [&str; 58] = [
+    "AK", "AL", "AR", "AS", "AZ", "CA", "CO", "CT", "DC", "DE", "FL", "GA", "GU", "HI", "IA",
+    "ID", "IL", "IN", "KS", "KY", "LA", "MA", "MD", "ME", "MH", "MI", "MN", "MO", "MP", "MS",
+    "MT", "NC", "ND", "NE", "NH", "NJ", "NM", "NV", "NY", "OH", "OK", "OR", "PA", "PR", "PW",
+    "RI", "SC", "SD", "TN", "TX", "UT", "VA", "VI", "VT", "WA", "WI", "WV", "WY",
+];
+
+/// Marine areas, which only have zones (ex "PZZ530").
+const MARINE_AREAS: [&str; 15] = [
+    "AM", "AN", "GM", "LC", "LE", "LH", "LM", "LO", "LS", "PH", "PK", "PM", "PS", "PZ", "SL",
+];
+
+/// Check if text is formatted like an NWS zone or county code (ex "CAC007", "CAZ215").
+/// This doesn't check that the zone exists, see [`NwsSource::fetch_zone_names`].
+pub fn is_zone_code(code: &str) -> bool {
+    if code.len() != 6 || !code.is_ascii() {
+        return false;
+    }
+    let (area, kind, number) = (&code[..2], &code[2..3], &code[3..]);
+    if !number.bytes().all(|b| b.is_ascii_digit()) {
+        return false;
+    }
+
+    match kind {
+        "C" => STATES.contains(&area),
+        "Z" => STATES.contains(&area) || MARINE_AREAS.contains(&area),
+        _ => false,
+    }
+}
+
+/// Alert source backed by the NWS API.
+pub struct NwsSource {
+    client: Client,
+    base_url: String,
+    /// Responses to alert requests
+    cache: ResponseCache<Vec<Alert>>,
+}
+
+impl NwsSource {
+    /// `base_url` is the root of the API, ex "https://api.weather.gov"
+    pub fn new(client: Client, base_url: &str) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache: ResponseCache::default(),
+        }
+    }
+
+    /// GET a URL and parse the JSON response.
+    async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        let response = self.client
+            .get(url)
+            .header(USER_AGENT, APP_USER_AGENT)
+            .send()
+            .await?;
+        Ok(check_status(response)?.json().await?)
+    }
+
+    /// GET a list of alerts, only downloading it again if it changed since the last request.
+    async fn get_alerts(&self, url: &str) -> Result<Vec<Alert>, Error> {
+        self.cache.get(&self.client, url, FeatureCollection::into_alerts).await
+    }
+
+    /// Look up the names of zones, ex "CAC007" is "Butte, CA".
+    /// Zones NWS doesn't know about are left out.
+    pub async fn fetch_zone_names(&self, zones: &[&str]) -> Result<HashMap<String, String>, Error> {
+        if zones.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let url = format!("{}/zones?id={}", self.base_url, zones.join(","));
+        let collection: ZoneCollection = self.get(&url).await?;
+
+        Ok(collection.features
+            .into_iter()
+            .map(|f| {
+                let zone = f.properties;
+                let name = match zone.state {
+                    Some(state) => format!("{}, {state}", zone.name),
+                    None => zone.name,
+                };
+                (zone.id, name)
+            })
+            .collect())
+    }
+
+    /// Fetch the forecast for a point, by looking up the
+    /// forecast office grid the point falls in.
+    pub async fn fetch_forecast(&self, point: Point) -> Result<Forecast, Error> {
+        let url = format!("{}/points/{point}", self.base_url);
+        let point: PointMetadata = self.get(&url).await?;
+        let forecast: GridpointForecast = self.get(&point.properties.forecast).await?;
+
+        let location = point.properties.relative_location
+            .map(|l| format!("{}, {}", l.properties.city, l.properties.state));
+        Ok(Forecast { location, periods: forecast.properties.periods })
+    }
+}
+
+impl AlertSource for NwsSource {
+    async fn fetch_active(&self, zone: &str) -> Result<Vec<Alert>, Error> {
+        self.get_alerts(&format!("{}/alerts/active?zone={zone}", self.base_url)).await
+    }
+
+    async fn fetch_active_at(&self, point: Point) -> Result<Vec<Alert>, Error> {
+        self.get_alerts(&format!("{}/alerts/active?point={point}", self.base_url)).await
+    }
+}
+
+/// Response body of `/alerts/active`.
+///
+/// Features are kept untyped here so that one malformed alert
+/// does not prevent the rest of the collection from parsing.
+#[derive(Debug, Deserialize)]
+pub struct FeatureCollection {
+    #[serde(default)]
+    pub features: Vec<Value>,
+}
+
+/// A single GeoJSON feature holding one alert.
+#[serde_as]
+#[derive(Debug, Deserialize)]
+pub struct Feature {
+    pub properties: Alert,
+    /// Zone based alerts have no geometry, and any
+    /// geometry other than polygons is ignored
+    #[serde_as(as = "DefaultOnError")]
+    #[serde(default)]
+    pub geometry: Option<Geometry>,
+}
+
+impl FeatureCollection {
+    /// Parse every feature into an [`Alert`], logging and
+    /// skipping any feature that fails to parse.
+    pub fn into_alerts(self) -> Vec<Alert> {
+        self.features
+            .into_iter()
+            .filter_map(|feature| {
+                let id = feature.get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or("<missing id>")
+                    .to_string();
+
+                match serde_json::from_value::<Feature>(feature) {
+                    Ok(f) => Some(Alert { geometry: f.geometry, ..f.properties }),
+                    Err(e) => {
+                        error!("Failed to parse NWS alert {id}: {e}");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Response body of `/zones`.
+#[derive(Debug, Deserialize)]
+struct ZoneCollection {
+    #[serde(default)]
+    features: Vec<ZoneFeature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ZoneFeature {
+    properties: Zone,
+}
+
+#[derive(Debug, Deserialize)]
+struct Zone {
+    /// Zone code, ex "CAC007"
+    id: String,
+    name: String,
+    /// Marine zones have no state
+    state: Option<String>,
+}
+
+/// Response body of `/points/{lat},{lon}`.
+#[derive(Debug, Deserialize)]
+struct PointMetadata {
+    properties: PointProperties,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PointProperties {
+    /// URL of the gridpoint forecast for the point
+    forecast: String,
+    relative_location: Option<RelativeLocation>,
+}
+
+/// Nearest city to a point.
+#[derive(Debug, Deserialize)]
+struct RelativeLocation {
+    properties: City,
+}
+
+#[derive(Debug, Deserialize)]
+struct City {
+    city: String,
+    state: String,
+}
+
+/// Response body of `/gridpoints/{office}/{x},{y}/forecast`.
+#[derive(Debug, Deserialize)]
+struct GridpointForecast {
+    properties: GridpointForecastProperties,
+}
+
+#[derive(Debug, Deserialize)]
+struct GridpointForecastProperties {
+    periods: Vec<ForecastPeriod>,
+}
+
+/// The forecast for a point, in 12 hour periods.
+#[derive(Debug)]
+pub struct Forecast {
+    /// Nearest city, ex "Chico, CA"
+    pub location: Option<String>,
+    pub periods: Vec<ForecastPeriod>,
+}
+
+/// A single day or night of a forecast.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForecastPeriod {
+    /// Ex "Tonight", "Saturday"
+    pub name: String,
+    pub temperature: Option<i32>,
+    pub temperature_unit: Option<String>,
+    pub short_forecast: String,
+    pub detailed_forecast: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_state_has_zones_and_counties() {
+        let states = [
+            "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "FL", "GA", "HI", "ID", "IL", "IN",
+            "IA", "KS", "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV",
+            "NH", "NJ", "NM", "NY", "NC", "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN",
+            "TX", "UT", "VT", "VA", "WA", "WV", "WI", "WY",
+            // Along with DC and the territories
+            "DC", "AS", "GU", "MH", "MP", "PR", "PW", "VI",
+        ];
+        assert_eq!(states.len(), STATES.len());
+        for state in states {
+            assert!(is_zone_code(&format!("{state}C001")), "{state} counties");
+            assert!(is_zone_code(&format!("{state}Z001")), "{state} zones");
+        }
+    }
+
+    #[test]
+    fn marine_areas_only_have_zones() {
+        assert!(is_zone_code("PZZ530"));
+        assert!(!is_zone_code("PZC530"));
+        assert!(!is_zone_code("XXZ001"));
+    }
+}
@@ -0,0 +1,87 @@
+//! Holds back alerts during quiet hours so they can be
+//! delivered as a digest once quiet hours end.
+
+use super::model::{Alert, MessageType};
+use crate::config::{AlertRoute, QuietHours};
+use poise::serenity_prelude::{ChannelId, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Quiet hours that apply to a route: its own if it has
+/// any, otherwise the global schedule.
+pub fn schedule_for<'a>(route: &'a AlertRoute, global: Option<&'a QuietHours>)
+-> Option<&'a QuietHours> {
+    route.quiet_hours.as_ref().or(global)
+}
+
+/// An alert held back during quiet hours, along with
+/// the routes and subscribers it would have been sent to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Queued {
+    pub alert: Alert,
+    /// Channels of the routes, which are looked up again when the alert is sent
+    pub channels: Vec<ChannelId>,
+    pub users: Vec<UserId>,
+}
+
+/// Alerts waiting for quiet hours to end, oldest first.
+/// Saved in the db file so held alerts survive a restart.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct QuietQueue {
+    queued: Vec<Queued>,
+}
+
+impl QuietQueue {
+    /// Hold an alert for the given routes and subscribers until their quiet hours end.
+    /// Updates replace the queued version of the alert they reference,
+    /// and cancellations remove it so it is never delivered.
+    pub fn push(&mut self, alert: Alert, channels: Vec<ChannelId>, users: Vec<UserId>) {
+        if let Some(q) = self.queued.iter_mut().find(|q| q.alert.id == alert.id) {
+            for channel_id in channels {
+                if !q.channels.contains(&channel_id) {
+                    q.channels.push(channel_id);
+                }
+            }
+            for user_id in users {
+                if !q.users.contains(&user_id) {
+                    q.users.push(user_id);
+                }
+            }
+            return;
+        }
+
+        let referenced: HashSet<&str> = alert.references.iter()
+            .map(|r| r.identifier.as_str())
+            .collect();
+        self.queued.retain(|q| !referenced.contains(q.alert.id.as_str()));
+
+        if alert.message_type != MessageType::Cancel {
+            self.queued.push(Queued { alert, channels, users });
+        }
+    }
+
+    /// Take the queued alerts for every route and subscriber that is no longer quiet.
+    pub fn take_ready(
+        &mut self,
+        is_quiet: impl Fn(ChannelId) -> bool,
+        is_user_quiet: impl Fn(UserId) -> bool,
+    ) -> Vec<Queued> {
+        let mut ready = Vec::new();
+        for q in &mut self.queued {
+            let (quiet, done): (Vec<ChannelId>, Vec<ChannelId>) = q.channels
+                .drain(..)
+                .partition(|c| is_quiet(*c));
+            q.channels = quiet;
+            let (quiet_users, done_users): (Vec<UserId>, Vec<UserId>) = q.users
+                .drain(..)
+                .partition(|u| is_user_quiet(*u));
+            q.users = quiet_users;
+
+            if !done.is_empty() || !done_users.is_empty() {
+                ready.push(Queued { alert: q.alert.clone(), channels: done, users: done_users });
+            }
+        }
+        self.queued.retain(|q| !q.channels.is_empty() || !q.users.is_empty());
+        ready
+    }
+}
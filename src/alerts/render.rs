@@ -0,0 +1,208 @@
+//! Renders alerts and forecasts as Discord embeds.
+//!
+//! Embed limits are documented here:
+//! https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+
+use super::fire::fire_weather;
+use super::model::{Alert, Severity};
+use super::nws::Forecast;
+use crate::chunk::{split_message, truncate};
+use chrono::{DateTime, FixedOffset, Utc};
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedFooter};
+
+const MAX_TITLE_LEN: usize = 256;
+const MAX_FIELD_LEN: usize = 1024;
+const MAX_FIELDS: usize = 25;
+const MAX_EMBED_LEN: usize = 6000;
+const MAX_DESCRIPTION_LEN: usize = 4096;
+
+/// Longest area description shown for each alert in a digest
+const MAX_DIGEST_AREA_LEN: usize = 100;
+
+/// What happened to an alert since it was first posted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AlertStatus {
+    New,
+    Updated,
+    Cancelled,
+}
+
+/// Embed colour for each CAP severity level.
+fn severity_colour(severity: Severity) -> Colour {
+    match severity {
+        Severity::Extreme => Colour::from_rgb(0x8B, 0x00, 0x8B),
+        Severity::Severe => Colour::RED,
+        Severity::Moderate => Colour::ORANGE,
+        Severity::Minor => Colour::GOLD,
+        Severity::Unknown => Colour::LIGHT_GREY,
+    }
+}
+
+/// Format a time as a Discord relative timestamp (ex "in 3 hours").
+fn relative_time(time: DateTime<FixedOffset>) -> String {
+    format!("<t:{}:R>", time.timestamp())
+}
+
+/// Build the embed shown for an alert.
+pub fn alert_embed(alert: &Alert, status: AlertStatus) -> CreateEmbed {
+    let title = match status {
+        AlertStatus::New => alert.event.clone(),
+        AlertStatus::Updated => format!("{} (updated)", alert.event),
+        AlertStatus::Cancelled => format!("~~{}~~ (cancelled)", alert.event),
+    };
+    let title = truncate(&title, MAX_TITLE_LEN);
+
+    let colour = match status {
+        AlertStatus::Cancelled => Colour::DARK_GREY,
+        _ => severity_colour(alert.severity),
+    };
+
+    let headline = alert.headline.clone().unwrap_or_default();
+    let footer = format!("{} • {:?} severity", alert.sender_name, alert.severity);
+
+    // Running total of embed text, which Discord caps at 6000 chars
+    let mut total_len = title.len() + headline.len() + footer.len();
+    let mut fields = Vec::new();
+
+    let areas = truncate(&alert.area_desc, MAX_FIELD_LEN);
+    total_len += "Areas".len() + areas.len();
+    fields.push((String::from("Areas"), areas, false));
+
+    if let Some(onset) = alert.onset {
+        let onset = relative_time(onset);
+        total_len += "Onset".len() + onset.len();
+        fields.push((String::from("Onset"), onset, true));
+    }
+    if let Some(ends) = alert.end_time() {
+        let ends = relative_time(ends);
+        total_len += "Ends".len() + ends.len();
+        fields.push((String::from("Ends"), ends, true));
+    }
+
+    // Fire weather specifics, so they don't have to be dug out of the details
+    if status != AlertStatus::Cancelled
+        && let Some(fire) = fire_weather(alert) {
+        let details = [
+            ("Wind", fire.wind, true),
+            ("Humidity", fire.humidity, true),
+            ("When", fire.timing, true),
+            ("Conditions", fire.conditions, false),
+        ];
+        for (name, text, inline) in details {
+            if let Some(text) = text {
+                let text = truncate(&text, MAX_FIELD_LEN);
+                total_len += name.len() + text.len();
+                fields.push((name.to_string(), text, inline));
+            }
+        }
+    }
+
+    // Cancelled alerts only need to say what was cancelled
+    if status != AlertStatus::Cancelled {
+        let instruction = alert.instruction.as_deref().unwrap_or_default();
+        let sections = [("Details", alert.description.as_str()), ("Instructions", instruction)];
+        for (name, text) in sections {
+            for (i, chunk) in split_message(text.trim(), MAX_FIELD_LEN).into_iter().enumerate() {
+                if chunk.trim().is_empty() {
+                    continue;
+                }
+                // Continuation fields get a blank name
+                let name = if i == 0 { name } else { "\u{200b}" };
+                if fields.len() == MAX_FIELDS
+                    || total_len + name.len() + chunk.len() > MAX_EMBED_LEN {
+                    break;
+                }
+                total_len += name.len() + chunk.len();
+                fields.push((name.to_string(), chunk, false));
+            }
+        }
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(title)
+        .colour(colour)
+        .fields(fields)
+        .footer(CreateEmbedFooter::new(footer))
+        .timestamp(alert.sent);
+
+    if !headline.is_empty() {
+        embed = embed.description(headline);
+    }
+    // NWS only gives an API link, which isn't worth showing
+    if let Some(link) = &alert.link {
+        embed = embed.url(link);
+    }
+
+    embed
+}
+
+/// Build the embed summarising the alerts collected for a digest,
+/// one line per alert for as many as fit.
+pub fn digest_embed(alerts: &[Alert], since: DateTime<Utc>) -> CreateEmbed {
+    let plural = if alerts.len() == 1 { "" } else { "s" };
+    let mut description = format!("{} alert{plural} since <t:{}:f>\n", alerts.len(), since.timestamp());
+
+    for (i, alert) in alerts.iter().enumerate() {
+        let event = match &alert.link {
+            Some(link) => format!("[{}]({link})", alert.event),
+            None => alert.event.clone(),
+        };
+        let mut line = format!(
+            "\n**{event}**, {:?} • {}",
+            alert.severity,
+            truncate(&alert.area_desc, MAX_DIGEST_AREA_LEN),
+        );
+        if let Some(ends) = alert.end_time() {
+            line.push_str(&format!(" • until <t:{}:f>", ends.timestamp()));
+        }
+
+        let more = format!("\n…and {} more", alerts.len() - i);
+        if description.len() + line.len() + more.len() > MAX_DESCRIPTION_LEN {
+            description.push_str(&more);
+            break;
+        }
+        description.push_str(&line);
+    }
+
+    let colour = alerts.iter()
+        .map(|a| a.severity)
+        .max()
+        .map_or(Colour::DARK_GREY, severity_colour);
+
+    CreateEmbed::new()
+        .title("Alert digest")
+        .colour(colour)
+        .description(description)
+}
+
+/// Build the embed shown for a point forecast, one field per period
+/// for as many periods as fit.
+pub fn forecast_embed(forecast: &Forecast, place: &str) -> CreateEmbed {
+    let title = truncate(&format!("Forecast for {place}"), MAX_TITLE_LEN);
+
+    let mut total_len = title.len();
+    let mut fields = Vec::new();
+    for period in &forecast.periods {
+        let name = match (period.temperature, &period.temperature_unit) {
+            (Some(temp), Some(unit)) => format!("{} • {temp}°{unit}", period.name),
+            _ => period.name.clone(),
+        };
+        let text = match period.detailed_forecast.trim() {
+            "" => &period.short_forecast,
+            detailed => detailed,
+        };
+        let text = truncate(text, MAX_FIELD_LEN);
+
+        if fields.len() == MAX_FIELDS || total_len + name.len() + text.len() > MAX_EMBED_LEN {
+            break;
+        }
+        total_len += name.len() + text.len();
+        fields.push((name, text, false));
+    }
+
+    CreateEmbed::new()
+        .title(title)
+        .colour(Colour::BLUE)
+        .fields(fields)
+        .footer(CreateEmbedFooter::new("National Weather Service"))
+}
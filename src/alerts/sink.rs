@@ -0,0 +1,130 @@
+//! Where alerts are delivered to.
+//!
+//! The alerts task only talks to Discord through [`AlertSink`]
+//! so that its logic can be exercised without a bot connection.
+
+use super::checkin::check_in_buttons;
+use super::model::Alert;
+use super::render::{AlertStatus, alert_embed, digest_embed};
+use crate::{Error, config::AlertRoute};
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::{
+    ChannelId, CreateAllowedMentions, CreateMessage,
+    EditMessage, Http, Mentionable, MessageId, RoleId, UserId,
+};
+use std::sync::Arc;
+
+/// Something alerts can be posted to and edited in.
+pub trait AlertSink {
+    /// Post a new alert to a route's channel.
+    async fn post(&self, alert: &Alert, route: &AlertRoute) -> Result<MessageId, Error>;
+
+    /// Post a summary of the alerts collected for a route in digest mode.
+    async fn digest(
+        &self,
+        alerts: &[Alert],
+        since: DateTime<Utc>,
+        route: &AlertRoute,
+    ) -> Result<MessageId, Error>;
+
+    /// Re-render a previously posted alert message.
+    async fn edit(
+        &self,
+        alert: &Alert,
+        status: AlertStatus,
+        message: (ChannelId, MessageId),
+    ) -> Result<(), Error>;
+
+    /// Post a plain text notice to a channel.
+    async fn notice(&self, channel_id: ChannelId, text: &str) -> Result<(), Error>;
+
+    /// Send a new alert to a subscriber by DM.
+    /// Returns the DM channel and message so the alert can be edited later.
+    async fn dm(&self, alert: &Alert, user_id: UserId) -> Result<(ChannelId, MessageId), Error>;
+
+    /// Send a plain text notice to a subscriber by DM.
+    async fn dm_notice(&self, user_id: UserId, text: &str) -> Result<(), Error>;
+}
+
+/// Mention any of a route's roles that the alerts call for.
+fn mention_roles(message: CreateMessage, route: &AlertRoute, alerts: &[Alert]) -> CreateMessage {
+    let mut roles: Vec<RoleId> = route.mentions.iter()
+        .filter(|m| alerts.iter().any(|a| m.matches(a)))
+        .map(|m| RoleId::new(m.role))
+        .collect();
+    roles.sort();
+    roles.dedup();
+
+    // Only ever ping the configured roles, never anything in the alert text
+    let mut message = message.allowed_mentions(CreateAllowedMentions::new().roles(roles.clone()));
+    if !roles.is_empty() {
+        let content = roles.iter()
+            .map(|r| r.mention().to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        message = message.content(content);
+    }
+    message
+}
+
+impl AlertSink for Arc<Http> {
+    /// Post an alert to a route's channel as an embed with check-in buttons,
+    /// mentioning any of the route's roles the alert calls for.
+    async fn post(&self, alert: &Alert, route: &AlertRoute) -> Result<MessageId, Error> {
+        let message = CreateMessage::new()
+            .embed(alert_embed(alert, AlertStatus::New))
+            .components(vec![check_in_buttons()]);
+        let message = mention_roles(message, route, std::slice::from_ref(alert));
+
+        let channel_id = ChannelId::new(route.channel);
+        Ok(channel_id.send_message(self, message).await?.id)
+    }
+
+    /// Post a digest as a single embed, mentioning any of the route's
+    /// roles that one of its alerts calls for.
+    async fn digest(
+        &self,
+        alerts: &[Alert],
+        since: DateTime<Utc>,
+        route: &AlertRoute,
+    ) -> Result<MessageId, Error> {
+        let message = CreateMessage::new().embed(digest_embed(alerts, since));
+        let message = mention_roles(message, route, alerts);
+
+        let channel_id = ChannelId::new(route.channel);
+        Ok(channel_id.send_message(self, message).await?.id)
+    }
+
+    /// Replace the embed of a previously posted alert message.
+    /// Check-ins are closed once an alert is cancelled.
+    async fn edit(
+        &self,
+        alert: &Alert,
+        status: AlertStatus,
+        (channel_id, message_id): (ChannelId, MessageId),
+    ) -> Result<(), Error> {
+        let mut edit = EditMessage::new().embed(alert_embed(alert, status));
+        if status == AlertStatus::Cancelled {
+            edit = edit.components(Vec::new());
+        }
+        channel_id.edit_message(self, message_id, edit).await?;
+        Ok(())
+    }
+
+    async fn notice(&self, channel_id: ChannelId, text: &str) -> Result<(), Error> {
+        channel_id.say(self, text).await?;
+        Ok(())
+    }
+
+    async fn dm(&self, alert: &Alert, user_id: UserId) -> Result<(ChannelId, MessageId), Error> {
+        let message = CreateMessage::new().embed(alert_embed(alert, AlertStatus::New));
+        let channel = user_id.create_dm_channel(self).await?;
+        let message = channel.id.send_message(self, message).await?;
+        Ok((message.channel_id, message.id))
+    }
+
+    async fn dm_notice(&self, user_id: UserId, text: &str) -> Result<(), Error> {
+        user_id.create_dm_channel(self).await?.say(self, text).await?;
+        Ok(())
+    }
+}
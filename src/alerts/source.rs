@@ -0,0 +1,142 @@
+//! Where alerts come from.
+
+use super::{geo::Point, model::Alert};
+use crate::{Error, config::{AirQualityWatch, CapFeed, EarthquakeWatch}};
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Client, Response, StatusCode,
+    header::{ETAG, HeaderMap, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER, USER_AGENT},
+};
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, fmt, sync::Mutex, time::Duration};
+
+/// User-Agent sent with every request the bot makes
+pub const APP_USER_AGENT: &str = "rust-web-api-client";
+
+/// Something that can be asked for the alerts active in an area.
+pub trait AlertSource {
+    /// Fetch the currently active alerts for a zone or county code.
+    async fn fetch_active(&self, area: &str) -> Result<Vec<Alert>, Error>;
+
+    /// Fetch the currently active alerts covering a point.
+    async fn fetch_active_at(&self, point: Point) -> Result<Vec<Alert>, Error>;
+
+    /// Fetch the currently active alerts in a CAP feed.
+    /// Sources that only cover their own areas don't take feeds.
+    async fn fetch_feed(&self, feed: &CapFeed) -> Result<Vec<Alert>, Error> {
+        Err(format!("can't fetch the feed at {}", feed.url).into())
+    }
+
+    /// Fetch alerts for recent earthquakes near any of `points`.
+    async fn fetch_earthquakes(&self, watch: &EarthquakeWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        Err(format!("can't fetch earthquakes {}", watch.key(points)).into())
+    }
+
+    /// Check if air quality can be fetched at all, so watches are skipped
+    /// instead of failing every check.
+    fn checks_air_quality(&self) -> bool {
+        true
+    }
+
+    /// Fetch alerts for poor air quality near any of `points`.
+    async fn fetch_air_quality(&self, watch: &AirQualityWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        Err(format!("can't fetch air quality {}", watch.key(points)).into())
+    }
+}
+
+/// Returned by a source when it is asked to slow down (ex HTTP 429 or 503).
+#[derive(Debug)]
+pub struct Throttled {
+    /// How long the source asked to wait before trying again
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.retry_after {
+            Some(delay) => write!(f, "feed is busy, retry after {}s", delay.as_secs()),
+            None => write!(f, "feed is busy"),
+        }
+    }
+}
+
+impl std::error::Error for Throttled {}
+
+/// Parse a `Retry-After` header, given either in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Turn rate limiting and error statuses into errors.
+pub fn check_status(response: Response) -> Result<Response, Error> {
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            Err(Box::new(Throttled { retry_after: retry_after(response.headers()) }))
+        }
+        _ => Ok(response.error_for_status()?),
+    }
+}
+
+/// Last response for a URL, reused when the server says nothing has changed.
+struct Cached<T> {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: T,
+}
+
+/// Responses to feed requests, keyed by URL, so feeds that haven't
+/// changed aren't downloaded and parsed again.
+pub struct ResponseCache<T>(Mutex<HashMap<String, Cached<T>>>);
+
+impl<T> Default for ResponseCache<T> {
+    fn default() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+}
+
+impl<T: Clone> ResponseCache<T> {
+    /// GET a URL and parse the JSON response with `parse`, only downloading it
+    /// again if it changed since the last request (ETag or Last-Modified).
+    pub async fn get<B: DeserializeOwned>(&self, client: &Client, url: &str, parse: impl FnOnce(B) -> T)
+    -> Result<T, Error> {
+        let mut request = client
+            .get(url)
+            .header(USER_AGENT, APP_USER_AGENT);
+        if let Some(cached) = self.0.lock().unwrap().get(url) {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED
+            && let Some(cached) = self.0.lock().unwrap().get(url) {
+            return Ok(cached.body.clone());
+        }
+        let response = check_status(response)?;
+
+        let header = |name| response.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+
+        let body = parse(response.json().await?);
+        self.0.lock().unwrap().insert(url.to_string(), Cached {
+            etag,
+            last_modified,
+            body: body.clone(),
+        });
+        Ok(body)
+    }
+}
@@ -0,0 +1,76 @@
+//! Alerts sent straight to members by DM.
+
+use crate::config::QuietHours;
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Most zones one member can get alerts for by DM
+pub const MAX_ZONES_PER_MEMBER: usize = 5;
+
+/// Most different zones members can get alerts for by DM between them,
+/// as each one is another request to NWS on every check
+pub const MAX_SUBSCRIBED_ZONES: usize = 50;
+
+/// A member's DM alert settings, saved in the db file.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Subscription {
+    /// NWS zone or county codes to get alerts for
+    pub zones: BTreeSet<String>,
+    /// Times to hold back DMs, none unless the member sets some
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Members subscribed to any of the given areas.
+pub fn subscribers_for(subscriptions: &HashMap<UserId, Subscription>, areas: &HashSet<String>)
+-> Vec<UserId> {
+    subscriptions.iter()
+        .filter(|(_, s)| s.zones.iter().any(|z| areas.contains(z)))
+        .map(|(user_id, _)| *user_id)
+        .collect()
+}
+
+/// Check a member can subscribe to another zone without going over the limits,
+/// returning the reason they can't.
+pub fn can_subscribe(subscriptions: &HashMap<UserId, Subscription>, user_id: UserId, zone: &str)
+-> Result<(), String> {
+    let own = subscriptions.get(&user_id).map_or(0, |s| s.zones.len());
+    if own >= MAX_ZONES_PER_MEMBER {
+        return Err(format!("You can only subscribe to {MAX_ZONES_PER_MEMBER} zones, unsubscribe from one first"));
+    }
+
+    let zones: HashSet<&str> = subscriptions.values()
+        .flat_map(|s| s.zones.iter().map(String::as_str))
+        .collect();
+    if !zones.contains(zone) && zones.len() >= MAX_SUBSCRIBED_ZONES {
+        return Err(format!("Members are already subscribed to {MAX_SUBSCRIBED_ZONES} different zones, which is as many as the bot checks"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscribed(zones: impl IntoIterator<Item = String>) -> Subscription {
+        Subscription { zones: zones.into_iter().collect(), quiet_hours: None }
+    }
+
+    #[test]
+    fn members_have_a_zone_limit() {
+        let full = subscribed((0..MAX_ZONES_PER_MEMBER).map(|i| format!("CAC{i:03}")));
+        let subscriptions = HashMap::from([(UserId::new(1), full)]);
+        assert!(can_subscribe(&subscriptions, UserId::new(1), "CAC100").is_err());
+        assert!(can_subscribe(&subscriptions, UserId::new(2), "CAC100").is_ok());
+    }
+
+    #[test]
+    fn zones_are_limited_across_members() {
+        let subscriptions: HashMap<UserId, Subscription> = (0..MAX_SUBSCRIBED_ZONES as u64)
+            .map(|i| (UserId::new(i + 1), subscribed([format!("CAC{i:03}")])))
+            .collect();
+        assert!(can_subscribe(&subscriptions, UserId::new(1000), "CAC100").is_err());
+        // Zones someone already gets alerts for don't add to the checks
+        assert!(can_subscribe(&subscriptions, UserId::new(1000), "CAC001").is_ok());
+    }
+}
@@ -0,0 +1,966 @@
+//! End-to-end tests of the alerts task, using recorded NWS responses
+//! in place of the API and a recording sink in place of Discord.
+
+use super::*;
+use chrono::TimeDelta;
+use nws::FeatureCollection;
+use usgs::QuakeCollection;
+use std::sync::Mutex as StdMutex;
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers::{header, method, path, query_param}};
+
+const ACTIVE: &str = include_str!("fixtures/active.json");
+const UPDATE: &str = include_str!("fixtures/update.json");
+const CANCEL: &str = include_str!("fixtures/cancel.json");
+const POINTS: &str = include_str!("fixtures/points.json");
+const FORECAST: &str = include_str!("fixtures/forecast.json");
+const TORNADO: &str = include_str!("fixtures/tornado.json");
+const ZONES: &str = include_str!("fixtures/zones.json");
+const CAP_FEED: &str = include_str!("fixtures/cap_feed.xml");
+const CAP_ALERT: &str = include_str!("fixtures/cap_alert.xml");
+const CAP_CANCEL: &str = include_str!("fixtures/cap_cancel.xml");
+const QUAKES: &str = include_str!("fixtures/quakes.json");
+const AIRNOW: &str = include_str!("fixtures/airnow.json");
+
+const ORIGINAL_ID: &str = "urn:oid:2.49.0.1.840.0.rfw.001.1";
+
+/// Serves recorded responses, keyed by area, point or feed URL.
+/// Feeds are served a single CAP document, earthquakes come from "usgs"
+/// and air quality from "airnow".
+#[derive(Default)]
+struct FixtureSource {
+    responses: StdMutex<HashMap<String, &'static str>>,
+    /// Fail every NWS request while set
+    down: StdMutex<bool>,
+    /// Feed URLs that ask to be retried in an hour
+    throttled_feeds: StdMutex<HashSet<String>>,
+    /// Act as if there is no AirNow API key
+    no_air_key: bool,
+    air_alerted: StdMutex<airnow::Alerted>,
+}
+
+impl FixtureSource {
+    fn set(&self, area: &str, body: &'static str) {
+        self.responses.lock().unwrap().insert(area.to_string(), body);
+    }
+
+    fn set_down(&self, down: bool) {
+        *self.down.lock().unwrap() = down;
+    }
+
+    fn throttle_feed(&self, url: &str) {
+        self.throttled_feeds.lock().unwrap().insert(url.to_string());
+    }
+}
+
+impl AlertSource for FixtureSource {
+    async fn fetch_active(&self, area: &str) -> Result<Vec<Alert>, Error> {
+        if *self.down.lock().unwrap() {
+            return Err("feed unavailable".into());
+        }
+        let body = self.responses.lock().unwrap()
+            .get(area)
+            .copied()
+            .unwrap_or(r#"{"features": []}"#);
+        let collection: FeatureCollection = serde_json::from_str(body)?;
+        Ok(collection.into_alerts())
+    }
+
+    async fn fetch_active_at(&self, point: Point) -> Result<Vec<Alert>, Error> {
+        self.fetch_active(&point.to_string()).await
+    }
+
+    async fn fetch_feed(&self, feed: &CapFeed) -> Result<Vec<Alert>, Error> {
+        if self.throttled_feeds.lock().unwrap().contains(&feed.url) {
+            return Err(Box::new(Throttled { retry_after: Some(Duration::from_hours(1)) }));
+        }
+        let body = self.responses.lock().unwrap().get(&feed.url).copied();
+        let language = feed.language.as_deref().unwrap_or("en");
+        Ok(body.and_then(|b| cap::parse_alert(b, language, &feed.url)).into_iter().collect())
+    }
+
+    async fn fetch_earthquakes(&self, watch: &EarthquakeWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        let body = self.responses.lock().unwrap().get("usgs").copied().unwrap_or(r#"{"features": []}"#);
+        let quakes: QuakeCollection = serde_json::from_str(body)?;
+        Ok(quakes.near(watch, points))
+    }
+
+    fn checks_air_quality(&self) -> bool {
+        !self.no_air_key
+    }
+
+    async fn fetch_air_quality(&self, watch: &AirQualityWatch, _points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        assert!(!self.no_air_key, "air quality fetched without a key");
+        let body = self.responses.lock().unwrap().get("airnow").copied().unwrap_or("[]");
+        let observations: Vec<airnow::Observation> = serde_json::from_str(body)?;
+        Ok(airnow::over_threshold(&observations, watch, &mut self.air_alerted.lock().unwrap()))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Event {
+    Post { alert_id: String, channel: u64 },
+    Edit { alert_id: String, status: AlertStatus, message: u64 },
+    Notice { channel: u64 },
+    Dm { alert_id: String, user: u64 },
+    DmNotice { user: u64 },
+    Digest { alert_ids: Vec<String>, channel: u64 },
+}
+
+/// Records everything that would have been sent to Discord.
+#[derive(Default)]
+struct RecordingSink {
+    events: StdMutex<Vec<Event>>,
+}
+
+impl RecordingSink {
+    fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut self.events.lock().unwrap())
+    }
+}
+
+impl AlertSink for RecordingSink {
+    async fn post(&self, alert: &Alert, route: &AlertRoute) -> Result<MessageId, Error> {
+        let mut events = self.events.lock().unwrap();
+        events.push(Event::Post { alert_id: alert.id.clone(), channel: route.channel });
+        Ok(MessageId::new(events.len() as u64))
+    }
+
+    async fn digest(
+        &self,
+        alerts: &[Alert],
+        _since: DateTime<Utc>,
+        route: &AlertRoute,
+    ) -> Result<MessageId, Error> {
+        let mut events = self.events.lock().unwrap();
+        events.push(Event::Digest {
+            alert_ids: alerts.iter().map(|a| a.id.clone()).collect(),
+            channel: route.channel,
+        });
+        Ok(MessageId::new(events.len() as u64))
+    }
+
+    async fn edit(
+        &self,
+        alert: &Alert,
+        status: AlertStatus,
+        (_, message_id): (ChannelId, MessageId),
+    ) -> Result<(), Error> {
+        self.events.lock().unwrap().push(Event::Edit {
+            alert_id: alert.id.clone(),
+            status,
+            message: message_id.get(),
+        });
+        Ok(())
+    }
+
+    async fn notice(&self, channel_id: ChannelId, _text: &str) -> Result<(), Error> {
+        self.events.lock().unwrap().push(Event::Notice { channel: channel_id.get() });
+        Ok(())
+    }
+
+    async fn dm(&self, alert: &Alert, user_id: UserId) -> Result<(ChannelId, MessageId), Error> {
+        let mut events = self.events.lock().unwrap();
+        events.push(Event::Dm { alert_id: alert.id.clone(), user: user_id.get() });
+        // DM channels are numbered after the user so they can't clash with routes
+        Ok((ChannelId::new(1000 + user_id.get()), MessageId::new(events.len() as u64)))
+    }
+
+    async fn dm_notice(&self, user_id: UserId, _text: &str) -> Result<(), Error> {
+        self.events.lock().unwrap().push(Event::DmNotice { user: user_id.get() });
+        Ok(())
+    }
+}
+
+fn config(extra: &str) -> Config {
+    toml::from_str(&format!(r#"
+        [alerts]
+        check_interval = 60
+        exclude_events = ["Special Weather Statement"]
+
+        [[alerts.routes]]
+        channel = 1
+        areas = ["CAC007"]
+
+        {extra}
+    "#)).expect("valid config")
+}
+
+fn utc(s: &str) -> DateTime<Utc> {
+    s.parse().expect("valid timestamp")
+}
+
+fn post(alert_id: &str, channel: u64) -> Event {
+    Event::Post { alert_id: alert_id.to_string(), channel }
+}
+
+fn dm(alert_id: &str, user: u64) -> Event {
+    Event::Dm { alert_id: alert_id.to_string(), user }
+}
+
+fn subscriptions(toml: &str) -> HashMap<UserId, Subscription> {
+    let subscription: Subscription = toml::from_str(toml).expect("valid subscription");
+    HashMap::from([(UserId::new(7), subscription)])
+}
+
+#[tokio::test]
+async fn nws_source_fetches_over_http() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/alerts/active"))
+        .and(query_param("zone", "CAC007"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ACTIVE))
+        .mount(&server)
+        .await;
+
+    let source = NwsSource::new(Client::new(), &server.uri());
+    let alerts = source.fetch_active("CAC007").await.expect("alerts");
+
+    // The malformed feature is skipped rather than failing the whole response
+    let events: Vec<&str> = alerts.iter().map(|a| a.event.as_str()).collect();
+    assert_eq!(events, ["Red Flag Warning", "Special Weather Statement"]);
+    assert_eq!(alerts[0].severity, Severity::Severe);
+    assert!(alerts[1].ends.is_none());
+}
+
+#[tokio::test]
+async fn nws_source_reports_http_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let source = NwsSource::new(Client::new(), &server.uri());
+    assert!(source.fetch_active("CAC007").await.is_err());
+}
+
+#[tokio::test]
+async fn nws_source_fetches_alerts_for_a_point() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/alerts/active"))
+        .and(query_param("point", "39.7300,-121.8400"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ACTIVE))
+        .mount(&server)
+        .await;
+
+    let source = NwsSource::new(Client::new(), &server.uri());
+    let alerts = source.fetch_active_at(Point { lat: 39.73, lon: -121.84 }).await.expect("alerts");
+    assert_eq!(alerts.len(), 2);
+}
+
+#[tokio::test]
+async fn nws_source_follows_points_to_the_forecast() {
+    let server = MockServer::start().await;
+    // The points response links to the forecast with an absolute URL
+    let points = POINTS.replace("https://api.weather.gov", &server.uri());
+    Mock::given(method("GET"))
+        .and(path("/points/39.7300,-121.8400"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(points))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/gridpoints/STO/41,120/forecast"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(FORECAST))
+        .mount(&server)
+        .await;
+
+    let source = NwsSource::new(Client::new(), &server.uri());
+    let forecast = source.fetch_forecast(Point { lat: 39.73, lon: -121.84 }).await.expect("forecast");
+    assert_eq!(forecast.location.as_deref(), Some("Chico, CA"));
+    let periods: Vec<&str> = forecast.periods.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(periods, ["Today", "Tonight"]);
+    assert_eq!(forecast.periods[0].temperature, Some(84));
+}
+
+#[tokio::test]
+async fn nws_source_reuses_unchanged_responses() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(header("If-None-Match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200)
+            .insert_header("ETag", "\"v1\"")
+            .set_body_string(ACTIVE))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let source = NwsSource::new(Client::new(), &server.uri());
+    let first = source.fetch_active("CAC007").await.expect("alerts");
+    let second = source.fetch_active("CAC007").await.expect("cached alerts");
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn nws_source_reports_retry_after() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+        .mount(&server)
+        .await;
+
+    let source = NwsSource::new(Client::new(), &server.uri());
+    let err = source.fetch_active("CAC007").await.expect_err("rate limited");
+    let throttled = err.downcast_ref::<Throttled>().expect("throttled");
+    assert_eq!(throttled.retry_after, Some(Duration::from_secs(120)));
+}
+
+#[tokio::test]
+async fn zone_names_are_looked_up_on_load() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/zones"))
+        .and(query_param("id", "CAC007,PZZ530"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ZONES))
+        .mount(&server)
+        .await;
+
+    let mut cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        areas = ["PZZ530", "CAC007"]
+    "#);
+    cfg.alerts.nws_url = server.uri();
+    cfg.resolve_zone_names(&Client::new()).await.expect("known zones");
+    assert_eq!(cfg.zone_label("CAC007"), "CAC007 (Butte, CA)");
+    assert!(cfg.zone_label("PZZ530").starts_with("PZZ530 (San Pablo Bay"));
+}
+
+#[tokio::test]
+async fn unknown_zones_are_rejected() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/zones"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(ZONES))
+        .mount(&server)
+        .await;
+
+    // CAC999 is formatted correctly, but doesn't exist
+    let source = r#"[alerts]
+check_interval = 60
+
+[[alerts.routes]]
+channel = 1472708201211494562
+areas = ["CAC007", "CAC999"]
+"#;
+    let mut cfg = crate::config::parse_config(source).expect("valid config");
+    cfg.alerts.nws_url = server.uri();
+    let err = cfg.resolve_zone_names(&Client::new()).await.expect_err("unknown zone");
+    assert_eq!(
+        err.locate(source).to_string(),
+        "config.toml:6: alerts.routes[0].areas[1]: NWS has no zone named `CAC999`",
+    );
+}
+
+#[tokio::test]
+async fn zones_are_unchecked_when_nws_is_down() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let mut cfg = config("");
+    cfg.alerts.nws_url = server.uri();
+    cfg.resolve_zone_names(&Client::new()).await.expect("config kept");
+    assert_eq!(cfg.zone_label("CAC007"), "CAC007");
+}
+
+#[tokio::test]
+async fn alerts_are_filtered_and_posted_once() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    let now = utc("2026-10-17T12:00:00Z");
+    assert!(state.poll(&cfg, &HashMap::new(), now, &source, &sink).await);
+    // The Special Weather Statement is excluded by the global filter
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+
+    assert!(!state.poll(&cfg, &HashMap::new(), now, &source, &sink).await);
+    assert_eq!(sink.take(), []);
+}
+
+#[tokio::test]
+async fn routes_only_get_alerts_for_their_areas() {
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        areas = ["CAC053"]
+
+        [[alerts.routes]]
+        channel = 3
+        areas = ["CAC007"]
+        events = ["Tornado Warning"]
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+}
+
+#[tokio::test]
+async fn updates_edit_the_original_message() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    let now = utc("2026-10-17T12:00:00Z");
+
+    source.set("CAC007", ACTIVE);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    sink.take();
+
+    source.set("CAC007", UPDATE);
+    assert!(state.poll(&cfg, &HashMap::new(), now, &source, &sink).await);
+    assert_eq!(sink.take(), [Event::Edit {
+        alert_id: String::from("urn:oid:2.49.0.1.840.0.rfw.001.2"),
+        status: AlertStatus::Updated,
+        message: 1,
+    }]);
+}
+
+#[tokio::test]
+async fn cancels_mark_the_original_message() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    let now = utc("2026-10-17T12:00:00Z");
+
+    source.set("CAC007", ACTIVE);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    sink.take();
+
+    source.set("CAC007", CANCEL);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Edit {
+        alert_id: ORIGINAL_ID.to_string(),
+        status: AlertStatus::Cancelled,
+        message: 1,
+    }]);
+}
+
+#[tokio::test]
+async fn quiet_hours_hold_alerts_until_they_end() {
+    let cfg = config(r#"
+        [quiet_hours]
+        timezone = "UTC"
+        [[quiet_hours.windows]]
+        start = "22:00"
+        end = "07:00"
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T23:00:00Z"), &source, &sink).await;
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T03:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), []);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Notice { channel: 1 }, post(ORIGINAL_ID, 1)]);
+}
+
+#[tokio::test]
+async fn held_alerts_survive_a_restart() {
+    let cfg = config(r#"
+        [quiet_hours]
+        timezone = "UTC"
+        [[quiet_hours.windows]]
+        start = "22:00"
+        end = "07:00"
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    assert!(state.poll(&cfg, &HashMap::new(), utc("2026-10-17T23:00:00Z"), &source, &sink).await);
+    assert_eq!(sink.take(), []);
+
+    // Round trip the queue through the db file format
+    let saved = serde_json::to_string(&state.queue).expect("queue serializes");
+    let mut state = AlertState {
+        alert_list: state.alert_list,
+        queue: serde_json::from_str(&saved).expect("queue deserializes"),
+        ..Default::default()
+    };
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Notice { channel: 1 }, post(ORIGINAL_ID, 1)]);
+}
+
+#[tokio::test]
+async fn severe_alerts_can_override_quiet_hours() {
+    let cfg = config(r#"
+        [quiet_hours]
+        timezone = "UTC"
+        override_severity = "Severe"
+        [[quiet_hours.windows]]
+        start = "22:00"
+        end = "07:00"
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T23:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+}
+
+const DAILY_DIGEST: &str = r#"
+    [alerts.routes.digest]
+    timezone = "UTC"
+    times = ["07:00"]
+"#;
+
+#[tokio::test]
+async fn digest_routes_post_a_summary_when_due() {
+    let cfg = config(DAILY_DIGEST);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    assert!(state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T06:59:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), []);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Digest { alert_ids: vec![ORIGINAL_ID.to_string()], channel: 1 }]);
+    assert_eq!(state.archived[0].messages, [(ChannelId::new(1), MessageId::new(1))]);
+
+    // Still active, but already in a digest
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-19T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), []);
+}
+
+#[tokio::test]
+async fn digests_only_keep_the_latest_version() {
+    let cfg = config(DAILY_DIGEST);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+
+    source.set("CAC007", ACTIVE);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    source.set("CAC007", UPDATE);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T13:00:00Z"), &source, &sink).await;
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Digest {
+        alert_ids: vec![String::from("urn:oid:2.49.0.1.840.0.rfw.001.2")],
+        channel: 1,
+    }]);
+}
+
+#[tokio::test]
+async fn cancelled_alerts_are_left_out_of_digests() {
+    let cfg = config(DAILY_DIGEST);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+
+    source.set("CAC007", ACTIVE);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    source.set("CAC007", CANCEL);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T13:00:00Z"), &source, &sink).await;
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-18T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), []);
+}
+
+#[tokio::test]
+async fn cleanup_removes_ended_alerts() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+
+    // The Red Flag Warning ends 2026-10-18 at 20:00 PDT
+    state.clear_ended_alerts(utc("2026-10-19T02:59:00Z"));
+    assert!(state.alert_list.contains_key(ORIGINAL_ID));
+    state.clear_ended_alerts(utc("2026-10-19T03:01:00Z"));
+    assert!(state.alert_list.is_empty());
+}
+
+#[tokio::test]
+async fn subscribers_get_alerts_by_dm_after_channels() {
+    // Subscribed to a zone no route watches, as well as one that is
+    let cfg = config("");
+    let subs = subscriptions(r#"zones = ["CAC007", "CAC053"]"#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC053", ACTIVE);
+    source.set("CAC007", ACTIVE);
+
+    let now = utc("2026-10-17T12:00:00Z");
+    assert!(state.poll(&cfg, &subs, now, &source, &sink).await);
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1), dm(ORIGINAL_ID, 7)]);
+
+    // Updates edit the DM along with the channel message
+    source.set("CAC007", UPDATE);
+    source.set("CAC053", UPDATE);
+    state.poll(&cfg, &subs, now, &source, &sink).await;
+    let edited: Vec<u64> = sink.take().into_iter()
+        .map(|e| match e {
+            Event::Edit { message, .. } => message,
+            e => panic!("expected only edits, got {e:?}"),
+        })
+        .collect();
+    assert_eq!(edited, [1, 2]);
+}
+
+#[tokio::test]
+async fn subscribers_follow_their_own_quiet_hours() {
+    // The channel has no quiet hours, but the subscriber does
+    let cfg = config("");
+    let subs = subscriptions(r#"
+        zones = ["CAC007"]
+        [quiet_hours]
+        timezone = "UTC"
+        [[quiet_hours.windows]]
+        start = "22:00"
+        end = "07:00"
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    state.poll(&cfg, &subs, utc("2026-10-17T23:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+
+    state.poll(&cfg, &subs, utc("2026-10-18T07:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [Event::DmNotice { user: 7 }, dm(ORIGINAL_ID, 7)]);
+}
+
+#[tokio::test]
+async fn polygon_alerts_only_reach_routes_they_cover() {
+    let cfg = config(r#"
+        # Inside the warning
+        [[alerts.routes]]
+        channel = 2
+        areas = ["CAC007"]
+        points = [{ lat = 39.73, lon = -121.84 }]
+
+        # Elsewhere in the county
+        [[alerts.routes]]
+        channel = 3
+        areas = ["CAC007"]
+        points = [{ lat = 39.50, lon = -121.60 }]
+
+        # Overlapping the east edge of the warning
+        [[alerts.routes]]
+        channel = 4
+        areas = ["CAC007"]
+        polygon = { type = "Polygon", coordinates = [[
+            [-121.80, 39.70], [-121.60, 39.70], [-121.60, 39.90], [-121.80, 39.90], [-121.80, 39.70],
+        ]] }
+
+        # Only a point, fetched on its own
+        [[alerts.routes]]
+        channel = 5
+        points = [{ lat = 39.73, lon = -121.84 }]
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", TORNADO);
+    source.set("39.7300,-121.8400", TORNADO);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T17:10:00Z"), &source, &sink).await;
+    let tornado = "urn:oid:2.49.0.1.840.0.tor.001.1";
+    assert_eq!(sink.take(), [post(tornado, 1), post(tornado, 2), post(tornado, 4), post(tornado, 5)]);
+}
+
+#[tokio::test]
+async fn zone_alerts_reach_every_route_in_the_zone() {
+    // The Red Flag Warning has no geometry, so it covers all of CAC007
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        areas = ["CAC007"]
+        points = [{ lat = 39.50, lon = -121.60 }]
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1), post(ORIGINAL_ID, 2)]);
+}
+
+#[tokio::test]
+async fn failed_checks_back_off() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    source.set_down(true);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    assert_eq!(state.health[&Provider::Nws].consecutive_failures, 1);
+
+    // Backing off for between half and all of the 60s check interval
+    source.set_down(false);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:29Z"), &source, &sink).await;
+    assert_eq!(sink.take(), []);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:01:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+    assert_eq!(state.health[&Provider::Nws].consecutive_failures, 0);
+}
+
+#[tokio::test]
+async fn degraded_feed_is_announced_once() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+
+    // Checks an hour apart are never still backing off
+    source.set_down(true);
+    for hour in 0..8 {
+        let now = utc("2026-10-17T00:00:00Z") + TimeDelta::hours(hour);
+        state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    }
+    assert_eq!(sink.take(), [Event::Notice { channel: 1 }]);
+
+    // Recovering is announced before the alerts that were missed
+    source.set_down(false);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T09:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Notice { channel: 1 }, post(ORIGINAL_ID, 1)]);
+}
+
+#[tokio::test]
+async fn failing_feeds_dont_hold_up_nws() {
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        [[alerts.routes.feeds]]
+        url = "https://feeds.example.org/cap.xml"
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+    source.throttle_feed("https://feeds.example.org/cap.xml");
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+
+    // The feed backs off for an hour, but NWS is still checked every minute
+    source.set("CAC007", UPDATE);
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:01:00Z"), &source, &sink).await;
+    let events = sink.take();
+    assert!(matches!(events[..], [Event::Edit { status: AlertStatus::Updated, message: 1, .. }]), "{events:?}");
+    assert_eq!(state.health[&Provider::Nws].consecutive_failures, 0);
+    assert_eq!(state.health[&Provider::for_feed("https://feeds.example.org/cap.xml")].consecutive_failures, 1);
+
+    // Only the route using the feed hears that it is degraded
+    for hour in 1..8 {
+        let now = utc("2026-10-17T12:00:00Z") + TimeDelta::hours(hour);
+        state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    }
+    assert_eq!(sink.take(), [Event::Notice { channel: 2 }]);
+}
+
+#[tokio::test]
+async fn delivered_alerts_are_archived() {
+    let cfg = config("");
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    let now = utc("2026-10-17T12:00:00Z");
+
+    source.set("CAC007", ACTIVE);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    source.set("CAC007", UPDATE);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+
+    // The update is archived with the message it edited
+    let [original, update] = &state.archived[..] else {
+        panic!("expected two archived alerts, got {:?}", state.archived);
+    };
+    assert_eq!(original.id, ORIGINAL_ID);
+    assert_eq!(original.event, "Red Flag Warning");
+    assert_eq!(original.areas, ["CAZ215", "CAZ278"]);
+    assert_eq!(update.message_type, MessageType::Update);
+    assert_eq!(update.messages, original.messages);
+
+    let query = HistoryQuery { event: Some(String::from("red flag")), ..Default::default() };
+    assert_eq!(query.search(&state.archived).len(), 2);
+}
+
+#[tokio::test]
+async fn cap_source_follows_feeds_to_documents_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/feed"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CAP_FEED))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/cap/wind-001.xml"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CAP_ALERT))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let source = CapSource::new(Client::new());
+    let feed = CapFeed { url: format!("{}/feed", server.uri()), language: None };
+    let alerts = source.fetch_feed(&feed).await.expect("alerts");
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].event, "gale-force gusts");
+
+    // Documents already seen aren't downloaded again
+    assert_eq!(source.fetch_feed(&feed).await.expect("cached alerts"), alerts);
+}
+
+#[tokio::test]
+async fn feed_alerts_are_posted_and_cancelled() {
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        [[alerts.routes.feeds]]
+        url = "https://example.com/cap.xml"
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    let now = utc("2026-10-17T12:00:00Z");
+    let id = "2.49.0.0.276.0.DWD.PVW.1760688000000.wind-001";
+
+    source.set("https://example.com/cap.xml", CAP_ALERT);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    assert_eq!(sink.take(), [post(id, 2)]);
+
+    source.set("https://example.com/cap.xml", CAP_CANCEL);
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    assert_eq!(sink.take(), [Event::Edit {
+        alert_id: id.to_string(),
+        status: AlertStatus::Cancelled,
+        message: 1,
+    }]);
+}
+
+#[tokio::test]
+async fn nearby_earthquakes_reach_routes_watching_for_them() {
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        [alerts.routes.earthquakes]
+        points = [{ lat = 39.73, lon = -121.84 }]
+
+        [[alerts.routes]]
+        channel = 3
+        [alerts.routes.earthquakes]
+        min_magnitude = 5.5
+        points = [{ lat = 61.2, lon = -149.9 }]
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("usgs", QUAKES);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T12:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post("nc75012345", 2), post("ak0261234", 3)]);
+}
+
+#[tokio::test]
+async fn usgs_source_fetches_the_summary_feed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/all_hour.geojson"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(QUAKES))
+        .mount(&server)
+        .await;
+
+    let source = UsgsSource::new(Client::new(), &format!("{}/all_hour.geojson", server.uri()));
+    let watch = EarthquakeWatch { min_magnitude: 4.0, max_distance_km: 100.0, points: Vec::new() };
+    let alerts = source.fetch_earthquakes(&watch, &[Point { lat: 39.73, lon: -121.84 }]).await
+        .expect("earthquakes");
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].link.as_deref(), Some("https://earthquake.usgs.gov/earthquakes/eventpage/nc75012345"));
+}
+
+#[tokio::test]
+async fn poor_air_quality_is_alerted_once() {
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        points = [{ lat = 39.73, lon = -121.84 }]
+        [alerts.routes.air_quality]
+        min_aqi = 151
+    "#);
+    let source = FixtureSource::default();
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("airnow", AIRNOW);
+
+    let now = utc("2026-10-17T19:00:00Z");
+    state.poll(&cfg, &HashMap::new(), now, &source, &sink).await;
+    assert_eq!(sink.take(), [post("airnow:Chico, CA:PM2.5:2026-10-17:4", 2)]);
+
+    // The same reading an hour later is already known
+    state.poll(&cfg, &HashMap::new(), now + TimeDelta::hours(1), &source, &sink).await;
+    assert_eq!(sink.take(), []);
+}
+
+#[tokio::test]
+async fn air_quality_is_skipped_without_a_key() {
+    let cfg = config(r#"
+        [[alerts.routes]]
+        channel = 2
+        points = [{ lat = 39.73, lon = -121.84 }]
+        [alerts.routes.air_quality]
+        min_aqi = 151
+    "#);
+    let source = FixtureSource { no_air_key: true, ..Default::default() };
+    let sink = RecordingSink::default();
+    let mut state = AlertState::default();
+    source.set("CAC007", ACTIVE);
+    source.set("airnow", AIRNOW);
+
+    state.poll(&cfg, &HashMap::new(), utc("2026-10-17T19:00:00Z"), &source, &sink).await;
+    assert_eq!(sink.take(), [post(ORIGINAL_ID, 1)]);
+    assert!(!state.health.contains_key(&Provider::AirNow));
+}
+
+#[tokio::test]
+async fn airnow_errors_hide_the_api_key() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/aq/observation/latLong/current/"))
+        .and(query_param("API_KEY", "secret-key"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let source = AirNowSource::new(Client::new(), &server.uri(), Some(String::from("secret-key")));
+    let watch = AirQualityWatch { min_aqi: 101, distance_miles: 25, points: Vec::new() };
+    let err = source.fetch_air_quality(&watch, &[Point { lat: 39.73, lon: -121.84 }]).await
+        .expect_err("server error");
+    assert!(!err.to_string().contains("secret-key"), "{err}");
+}
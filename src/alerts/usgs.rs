@@ -0,0 +1,220 @@
+//! Earthquakes from the USGS GeoJSON summary feeds.
+//!
+//! Each earthquake is turned into an [`Alert`] so it goes through the
+//! same routing, filters and embeds as weather alerts.
+//!
+//! https://earthquake.usgs.gov/earthquakes/feed/v1.0/geojson.php
+
+use super::{
+    geo::Point,
+    model::{Alert, Category, Certainty, Geocode, MessageType, Severity, Urgency},
+    source::ResponseCache,
+};
+use crate::{Error, config::EarthquakeWatch};
+use chrono::{DateTime, TimeDelta};
+use reqwest::Client;
+use serde::Deserialize;
+use std::convert::identity;
+
+/// How long an earthquake alert is kept around after the earthquake
+const ALERT_LIFETIME: TimeDelta = TimeDelta::days(1);
+
+/// Response body of a summary feed.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct QuakeCollection {
+    features: Vec<QuakeFeature>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct QuakeFeature {
+    id: String,
+    properties: QuakeProperties,
+    geometry: QuakeGeometry,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuakeProperties {
+    mag: Option<f64>,
+    mag_type: Option<String>,
+    place: Option<String>,
+    /// Milliseconds since the Unix epoch
+    time: i64,
+    url: String,
+    title: String,
+    /// PAGER alert level for expected damage, ex "orange"
+    alert: Option<String>,
+    /// 1 if a tsunami warning center has looked at the earthquake
+    tsunami: u8,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct QuakeGeometry {
+    /// Longitude, latitude and depth in km
+    coordinates: [f64; 3],
+}
+
+/// Severity of an earthquake from its magnitude,
+/// raised to match the damage PAGER expects.
+fn quake_severity(magnitude: f64, pager: Option<&str>) -> Severity {
+    let by_magnitude = match magnitude {
+        m if m >= 6.5 => Severity::Extreme,
+        m if m >= 5.0 => Severity::Severe,
+        m if m >= 4.0 => Severity::Moderate,
+        _ => Severity::Minor,
+    };
+    let by_pager = match pager {
+        Some("red") => Severity::Extreme,
+        Some("orange") => Severity::Severe,
+        Some("yellow") => Severity::Moderate,
+        _ => Severity::Unknown,
+    };
+    by_magnitude.max(by_pager)
+}
+
+impl QuakeFeature {
+    fn epicenter(&self) -> Point {
+        let [lon, lat, _] = self.geometry.coordinates;
+        Point { lat, lon }
+    }
+
+    fn to_alert(&self, magnitude: f64) -> Option<Alert> {
+        let p = &self.properties;
+        let time = DateTime::from_timestamp_millis(p.time)?.fixed_offset();
+        let place = p.place.clone().unwrap_or_else(|| self.epicenter().to_string());
+
+        let mut description = format!(
+            "Magnitude {magnitude:.1}{} earthquake {place}, {:.1} km deep.",
+            p.mag_type.as_ref().map(|t| format!(" ({t})")).unwrap_or_default(),
+            self.geometry.coordinates[2],
+        );
+        if let Some(level) = &p.alert {
+            description.push_str(&format!("\n\nPAGER alert level: {level}"));
+        }
+        if p.tsunami == 1 {
+            description.push_str("\n\nA tsunami warning center has looked at this earthquake, see https://www.tsunami.gov");
+        }
+
+        Some(Alert {
+            id: self.id.clone(),
+            area_desc: place,
+            geocode: Geocode::default(),
+            sent: time,
+            effective: time,
+            onset: Some(time),
+            expires: Some(time + ALERT_LIFETIME),
+            ends: None,
+            message_type: MessageType::Alert,
+            category: Category::Geo,
+            severity: quake_severity(magnitude, p.alert.as_deref()),
+            certainty: Certainty::Observed,
+            urgency: Urgency::Immediate,
+            event: String::from("Earthquake"),
+            sender_name: String::from("USGS"),
+            headline: Some(p.title.clone()),
+            description,
+            instruction: None,
+            references: Vec::new(),
+            link: Some(p.url.clone()),
+            geometry: None,
+        })
+    }
+}
+
+impl QuakeCollection {
+    /// Alerts for the earthquakes a watch is interested in.
+    pub fn near(&self, watch: &EarthquakeWatch, points: &[Point]) -> Vec<Alert> {
+        self.features.iter()
+            .filter(|f| f.properties.kind == "earthquake")
+            .filter_map(|f| {
+                let magnitude = f.properties.mag.filter(|&m| m >= watch.min_magnitude)?;
+                let epicenter = f.epicenter();
+                points.iter()
+                    .any(|p| p.distance_km(epicenter) <= watch.max_distance_km)
+                    .then(|| f.to_alert(magnitude))?
+            })
+            .collect()
+    }
+}
+
+impl EarthquakeWatch {
+    /// Key earthquakes found for this watch are kept under, ex "M4+ within 100km of 39.7300,-121.8400".
+    pub fn key(&self, points: &[Point]) -> String {
+        let points: Vec<String> = points.iter().map(Point::to_string).collect();
+        format!("M{}+ within {}km of {}", self.min_magnitude, self.max_distance_km, points.join(" "))
+    }
+}
+
+/// Earthquake source backed by a USGS summary feed.
+pub struct UsgsSource {
+    client: Client,
+    url: String,
+    cache: ResponseCache<QuakeCollection>,
+}
+
+impl UsgsSource {
+    /// `url` is a summary feed, ex ".../summary/all_hour.geojson"
+    pub fn new(client: Client, url: &str) -> Self {
+        Self { client, url: url.to_string(), cache: ResponseCache::default() }
+    }
+
+    /// GET the feed, only downloading it again if it changed since the last request.
+    async fn get(&self) -> Result<QuakeCollection, Error> {
+        self.cache.get(&self.client, &self.url, identity).await
+    }
+
+    /// Fetch alerts for the recent earthquakes a watch is interested in.
+    pub async fn fetch_earthquakes(&self, watch: &EarthquakeWatch, points: &[Point])
+    -> Result<Vec<Alert>, Error> {
+        Ok(self.get().await?.near(watch, points))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAKES: &str = include_str!("fixtures/quakes.json");
+
+    fn watch(min_magnitude: f64, max_distance_km: f64) -> EarthquakeWatch {
+        EarthquakeWatch { min_magnitude, max_distance_km, points: Vec::new() }
+    }
+
+    fn ids(alerts: Vec<Alert>) -> Vec<String> {
+        alerts.into_iter().map(|a| a.id).collect()
+    }
+
+    #[test]
+    fn earthquakes_are_filtered_by_magnitude_and_distance() {
+        let quakes: QuakeCollection = serde_json::from_str(QUAKES).unwrap();
+        let chico = [Point { lat: 39.73, lon: -121.84 }];
+
+        assert_eq!(ids(quakes.near(&watch(4.0, 100.0), &chico)), ["nc75012345"]);
+        assert_eq!(ids(quakes.near(&watch(2.5, 100.0), &chico)), ["nc75012345", "nc75012346"]);
+        // The Alaska earthquake is big enough, but too far away
+        assert_eq!(ids(quakes.near(&watch(4.0, 5000.0), &chico)), ["nc75012345", "ak0261234"]);
+    }
+
+    #[test]
+    fn earthquakes_become_alerts() {
+        let quakes: QuakeCollection = serde_json::from_str(QUAKES).unwrap();
+        let alerts = quakes.near(&watch(4.0, 100.0), &[Point { lat: 39.73, lon: -121.84 }]);
+        let alert = &alerts[0];
+
+        assert_eq!(alert.event, "Earthquake");
+        assert_eq!(alert.category, Category::Geo);
+        assert_eq!(alert.severity, Severity::Severe);
+        assert_eq!(alert.area_desc, "12 km NE of Chico, CA");
+        assert_eq!(alert.end_time(), Some(alert.sent + ALERT_LIFETIME));
+        assert!(alert.description.starts_with("Magnitude 5.1 (mw) earthquake 12 km NE of Chico, CA, 8.2 km deep."));
+    }
+
+    #[test]
+    fn pager_levels_raise_severity() {
+        assert_eq!(quake_severity(4.2, None), Severity::Moderate);
+        assert_eq!(quake_severity(4.2, Some("orange")), Severity::Severe);
+        assert_eq!(quake_severity(7.1, Some("green")), Severity::Extreme);
+    }
+}
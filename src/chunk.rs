@@ -0,0 +1,199 @@
+//! Splits long text into pieces that fit within Discord's limits.
+//!
+//! Text is split on paragraph, then line, then word boundaries,
+//! falling back on the nearest character boundary. Markdown code
+//! blocks that span a split are closed at the end of one chunk and
+//! reopened at the start of the next so formatting is preserved.
+
+use crate::{Context, Error};
+
+/// Max length of a Discord message in bytes.
+pub const MAX_MSG_LEN: usize = 2000;
+
+const FENCE: &str = "```";
+
+/// Closes an open code block at the end of a chunk.
+const FENCE_CLOSE: &str = "\n```";
+
+/// Longest language tag (ex "rust") carried over when reopening a code block
+const MAX_LANG_LEN: usize = 16;
+
+/// Find where to split `text` so the first part is at most `max_len` bytes.
+/// Returns the split index and the length of the separator found there.
+fn split_point(text: &str, max_len: usize) -> (usize, usize) {
+    let mut end = max_len.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let window = &text[..end];
+    for separator in ["\n\n", "\n", " "] {
+        if let Some(i) = window.rfind(separator)
+            && i > 0 {
+            return (i, separator.len());
+        }
+    }
+
+    // Always make progress, even if a single character doesn't fit
+    if end == 0 {
+        end = text.chars().next().map_or(0, char::len_utf8);
+    }
+    (end, 0)
+}
+
+/// Fence to reopen a code block with, keeping its language if it has one.
+/// Code on the opening line (ex "```let x = 1;") isn't repeated.
+fn reopen_fence(line: &str) -> String {
+    let lang = line[FENCE.len()..].trim();
+    let is_lang = !lang.is_empty()
+        && lang.len() <= MAX_LANG_LEN
+        && lang.chars().all(|c| c.is_ascii_alphanumeric() || "+-#._".contains(c));
+    if is_lang {
+        format!("{FENCE}{lang}")
+    } else {
+        FENCE.to_string()
+    }
+}
+
+/// Track whether a code block is still open after `text`.
+/// `open` holds the fence to reopen the current block with (ex "```rust").
+fn fence_after(text: &str, mut open: Option<String>) -> Option<String> {
+    for line in text.lines() {
+        let line = line.trim();
+        if !line.starts_with(FENCE) {
+            continue;
+        }
+        // A block opened and closed on one line doesn't change anything
+        if open.is_none() && line.len() > 2 * FENCE.len() && line.ends_with(FENCE) {
+            continue;
+        }
+        open = match open {
+            Some(_) => None,
+            None => Some(reopen_fence(line)),
+        };
+    }
+    open
+}
+
+/// Split a message into chunks of at most `max_len` bytes.
+pub fn split_message(message: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = message;
+    let mut open_fence: Option<String> = None;
+
+    while !rest.is_empty() {
+        // Reopen a code block carried over from the previous chunk
+        let mut chunk = match &open_fence {
+            Some(fence) => format!("{fence}\n"),
+            None => String::new(),
+        };
+
+        if chunk.len() + rest.len() <= max_len {
+            chunk.push_str(rest);
+            chunks.push(chunk);
+            break;
+        }
+
+        // Leave room to close a code block at the end of this chunk
+        let budget = max_len.saturating_sub(chunk.len() + FENCE_CLOSE.len());
+        let (i, separator_len) = split_point(rest, budget);
+        let head = &rest[..i];
+        rest = &rest[i + separator_len..];
+
+        open_fence = fence_after(head, open_fence);
+        chunk.push_str(head);
+        if open_fence.is_some() {
+            chunk.push_str(FENCE_CLOSE);
+        }
+        chunks.push(chunk);
+    }
+
+    if chunks.is_empty() {
+        chunks.push(String::new());
+    }
+    chunks
+}
+
+/// Cut text down to at most `max_len` bytes, marking the cut with "...".
+pub fn truncate(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+
+    let mut chunks = split_message(text, max_len.saturating_sub(3));
+    let mut first = chunks.swap_remove(0);
+    first.push_str("...");
+    first
+}
+
+/// Send a message, split over as many messages as needed.
+pub async fn say_chunked(ctx: Context<'_>, message: &str) -> Result<(), Error> {
+    for chunk in split_message(message, MAX_MSG_LEN) {
+        ctx.say(chunk).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Code blocks are balanced in every chunk.
+    fn assert_fences_closed(chunks: &[String]) {
+        for chunk in chunks {
+            assert_eq!(fence_after(chunk, None), None, "unclosed code block in {chunk:?}");
+        }
+    }
+
+    #[test]
+    fn short_messages_are_left_alone() {
+        assert_eq!(split_message("hello", 10), ["hello"]);
+        assert_eq!(split_message("", 10), [""]);
+    }
+
+    #[test]
+    fn multibyte_text_is_split_on_character_boundaries() {
+        let message = "é".repeat(25) + &"🦀".repeat(10);
+        let chunks = split_message(&message, 7);
+        assert!(chunks.iter().all(|c| c.len() <= 7), "{chunks:?}");
+        assert_eq!(chunks.concat(), message);
+    }
+
+    #[test]
+    fn paragraphs_then_lines_then_words_are_preferred() {
+        assert_eq!(split_message("one two\nthree\n\nfour five", 20), ["one two\nthree", "four five"]);
+        assert_eq!(split_message("one two\nthree four five", 20), ["one two", "three four five"]);
+        assert_eq!(split_message("one two three four five", 20), ["one two three", "four five"]);
+    }
+
+    #[test]
+    fn code_blocks_are_reopened_with_their_language() {
+        let message = format!("Look:\n```rust\n{}```", "let x = 1;\n".repeat(10));
+        let chunks = split_message(&message, 60);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.len() <= 60), "{chunks:?}");
+        assert_fences_closed(&chunks);
+        for chunk in &chunks[1..] {
+            assert!(chunk.starts_with("```rust\n"), "{chunk:?}");
+        }
+    }
+
+    #[test]
+    fn long_opening_lines_are_not_repeated() {
+        let message = format!("```let long_line = {};\n```", "x ".repeat(1200));
+        let chunks = split_message(&message, MAX_MSG_LEN);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.len() <= MAX_MSG_LEN));
+        assert_fences_closed(&chunks);
+        assert!(chunks[1].starts_with("```\n"), "{:?}", chunks[1]);
+    }
+
+    #[test]
+    fn truncate_marks_the_cut() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("one two three", 10), "one...");
+        let cut = truncate(&"é".repeat(10), 8);
+        assert!(cut.len() <= 8);
+        assert_eq!(cut, "é...");
+    }
+}
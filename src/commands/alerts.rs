@@ -0,0 +1,821 @@
+//! Commands for weather alerts.
+//!
+//! Route changes are written back to 'config.toml' and pushed straight
+//! to the alerts task, so they apply without waiting on a reload.
+//! DM subscriptions are kept per member in the db file.
+
+use crate::{
+    Context, Error,
+    alerts::{ArchivedAlert, CheckIns, FeedHealth, HistoryQuery, MessageType, Provider, can_subscribe, is_zone_code},
+    chunk::say_chunked,
+    commands::weather::nws,
+    config::{self, AlertFilter, AlertRoute, Config, QuietHours},
+};
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use poise::{CreateReply, serenity_prelude::{self as serenity, ChannelId, GuildId, Mentionable, MessageId, RoleId}};
+use std::{cmp::Reverse, collections::HashSet, fmt::Debug, sync::atomic::Ordering};
+use toml_edit::{Array, DocumentMut, Item, Table, value};
+
+/// Past alerts shown per page of `/alerts history` and `/alerts checkins`
+const HISTORY_PAGE_LEN: usize = 6;
+
+/// Longest area description shown for a past alert, as some list dozens of counties
+const MAX_AREA_LEN: usize = 100;
+
+/// Weather alert settings and subscriptions
+// Permissions are checked per subcommand, as anyone can subscribe
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands(
+        "status", "add_zone", "remove_zone", "set_channel", "filters",
+        "subscribe", "unsubscribe", "subscriptions", "quiet_hours", "history",
+        "checkins",
+    ),
+    subcommand_required
+)]
+pub async fn alerts(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the current alert settings
+#[poise::command(prefix_command, slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn status(ctx: Context<'_>) -> Result<(), Error> {
+    if !ctx.data().alerts_running.load(Ordering::Relaxed) {
+        ctx.say("Alerts are not running, check config.toml").await?;
+        return Ok(());
+    }
+    let tx = &ctx.data().config_tx;
+    let cfg = tx.borrow().clone();
+
+    let mut message = format!(
+        "**Checking every {}s**\nAll alerts: {}\n",
+        cfg.alerts.check_interval.as_secs(),
+        describe_filter(&cfg.alerts.filter),
+    );
+    if let Some(quiet_hours) = &cfg.quiet_hours {
+        message.push_str(&format!("Quiet hours: {quiet_hours}\n"));
+    }
+
+    for (i, route) in cfg.alerts.routes.iter().enumerate() {
+        message.push_str(&format!("\n**Route {}**\n{}", i + 1, describe_route(&cfg, route)));
+    }
+    if cfg.alerts.routes.is_empty() {
+        message.push_str("\nNo routes configured\n");
+    }
+
+    let tracked = ctx.data().db.lock().await.alerts.len();
+    message.push_str(&format!("\nTracking {tracked} active alert(s)"));
+    {
+        let health = ctx.data().feed_health.borrow();
+        if health.is_empty() {
+            message.push_str("\nNo feeds checked yet");
+        }
+        for (provider, health) in health.iter() {
+            message.push_str(&format!("\n{}", describe_health(provider, health)));
+        }
+    }
+
+    say_chunked(ctx, &message).await?;
+    Ok(())
+}
+
+/// Watch another zone on a route
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "add-zone",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn add_zone(
+    ctx: Context<'_>,
+    #[description = "NWS zone or county code (ex CAC007)"] zone: String,
+    #[description = "Route number from /alerts status, defaults to 1"] route: Option<usize>,
+) -> Result<(), Error> {
+    let zone = zone.trim().to_uppercase();
+    let route = route.unwrap_or(1);
+
+    update_config(ctx, &format!("Added {zone} to route {route}"), |doc| {
+        let areas = string_list(route_table(doc, route)?, "areas");
+        if areas.iter().any(|a| a.as_str() == Some(&zone)) {
+            return Err(format!("Route {route} already watches {zone}").into());
+        }
+        areas.push(zone.as_str());
+        Ok(())
+    }).await
+}
+
+/// Stop watching a zone on a route
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "remove-zone",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn remove_zone(
+    ctx: Context<'_>,
+    #[description = "NWS zone or county code (ex CAC007)"] zone: String,
+    #[description = "Route number from /alerts status, defaults to 1"] route: Option<usize>,
+) -> Result<(), Error> {
+    let zone = zone.trim().to_uppercase();
+    let route = route.unwrap_or(1);
+
+    update_config(ctx, &format!("Removed {zone} from route {route}"), |doc| {
+        let areas = string_list(route_table(doc, route)?, "areas");
+        let old_len = areas.len();
+        areas.retain(|a| a.as_str() != Some(&zone));
+        if areas.len() == old_len {
+            return Err(format!("Route {route} doesn't watch {zone}").into());
+        }
+        Ok(())
+    }).await
+}
+
+/// Send a route's alerts to a different channel
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only,
+    rename = "set-channel",
+    required_permissions = "MANAGE_GUILD"
+)]
+async fn set_channel(
+    ctx: Context<'_>,
+    #[description = "Channel to send alerts to"] channel: serenity::GuildChannel,
+    #[description = "Route number from /alerts status, defaults to 1"] route: Option<usize>,
+) -> Result<(), Error> {
+    let route = route.unwrap_or(1);
+
+    update_config(ctx, &format!("Route {route} now sends alerts to {}", channel.mention()), |doc| {
+        // TOML integers are signed, but snowflakes fit comfortably
+        route_table(doc, route)?["channel"] = value(channel.id.get() as i64);
+        Ok(())
+    }).await
+}
+
+/// Show or change which alerts are sent
+///
+/// Leave out every option to show the current filters. Lists are comma
+/// separated, use "none" to clear one.
+#[allow(clippy::too_many_arguments)]
+#[poise::command(prefix_command, slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn filters(
+    ctx: Context<'_>,
+    #[description = "Route number from /alerts status, leave out for all alerts"] route: Option<usize>,
+    #[description = "Categories to allow (ex Met, Fire)"] alert_types: Option<String>,
+    #[description = "Events to allow (ex Red Flag Warning)"] events: Option<String>,
+    #[description = "Events to always drop"] exclude_events: Option<String>,
+    #[description = "Lowest severity to send (Minor, Moderate, Severe, Extreme or Any)"]
+    min_severity: Option<String>,
+    #[description = "Lowest urgency to send (Past, Future, Expected, Immediate or Any)"]
+    min_urgency: Option<String>,
+    #[description = "Lowest certainty to send (Unlikely, Possible, Likely, Observed or Any)"]
+    min_certainty: Option<String>,
+) -> Result<(), Error> {
+    if !ctx.data().alerts_running.load(Ordering::Relaxed) {
+        ctx.say("Alerts are not running, check config.toml").await?;
+        return Ok(());
+    }
+    let tx = &ctx.data().config_tx;
+
+    let unchanged = alert_types.is_none() && events.is_none() && exclude_events.is_none()
+        && min_severity.is_none() && min_urgency.is_none() && min_certainty.is_none();
+    if unchanged {
+        let cfg = tx.borrow().clone();
+        let message = match route {
+            None => format!("All alerts: {}", describe_filter(&cfg.alerts.filter)),
+            Some(i) => match i.checked_sub(1).and_then(|i| cfg.alerts.routes.get(i)) {
+                Some(r) => format!("Route {i}: {}", describe_filter(&r.filter)),
+                None => format!("No route {i}, see /alerts status"),
+            },
+        };
+        ctx.say(message).await?;
+        return Ok(());
+    }
+
+    let target = match route {
+        Some(i) => format!("route {i}"),
+        None => String::from("all alerts"),
+    };
+    update_config(ctx, &format!("Updated filters for {target}"), |doc| {
+        let table = match route {
+            Some(i) => route_table(doc, i)?,
+            None => doc.get_mut("alerts")
+                .and_then(Item::as_table_mut)
+                .ok_or("Missing [alerts] in config.toml")?,
+        };
+
+        set_list(table, "alert_types", alert_types.as_deref());
+        set_list(table, "events", events.as_deref());
+        set_list(table, "exclude_events", exclude_events.as_deref());
+        set_threshold(table, "min_severity", min_severity.as_deref());
+        set_threshold(table, "min_urgency", min_urgency.as_deref());
+        set_threshold(table, "min_certainty", min_certainty.as_deref());
+        Ok(())
+    }).await
+}
+
+/// Get alerts for a zone by DM
+///
+/// Enter `!alerts subscribe CAC007`
+/// Each member can subscribe to up to 5 zones.
+#[poise::command(prefix_command, slash_command)]
+async fn subscribe(
+    ctx: Context<'_>,
+    #[description = "NWS zone or county code (ex CAC007)"] zone: String,
+) -> Result<(), Error> {
+    if !ctx.data().alerts_running.load(Ordering::Relaxed) {
+        ctx.say("Alerts are not running, so subscriptions can't be sent. Ask a moderator to check config.toml").await?;
+        return Ok(());
+    }
+
+    let zone = zone.trim().to_uppercase();
+    if !is_zone_code(&zone) {
+        ctx.say(format!("`{zone}` isn't a zone or county code (ex CAC007)")).await?;
+        return Ok(());
+    }
+
+    let subscribed = ctx.data().db.lock().await
+        .subscriptions
+        .get(&ctx.author().id)
+        .is_some_and(|s| s.zones.contains(&zone));
+    if subscribed {
+        ctx.say(format!("You're already subscribed to {zone}")).await?;
+        return Ok(());
+    }
+
+    // Zones NWS doesn't know about would fail every check
+    match nws(ctx).fetch_zone_names(&[&zone]).await {
+        Ok(names) if names.contains_key(&zone) => {}
+        Ok(_) => {
+            ctx.say(format!("NWS has no zone named `{zone}`")).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            ctx.say(format!("**Unable** to check {zone} with NWS, try again later: {e}")).await?;
+            return Ok(());
+        }
+    }
+
+    let mut db = ctx.data().db.lock().await;
+    if let Err(reason) = can_subscribe(&db.subscriptions, ctx.author().id, &zone) {
+        drop(db);
+        ctx.say(reason).await?;
+        return Ok(());
+    }
+    db.subscriptions.entry(ctx.author().id).or_default().zones.insert(zone.clone());
+    db.save(&ctx.data().db_path).await?;
+    drop(db);
+
+    ctx.say(format!("You'll get alerts for {zone} by DM")).await?;
+    Ok(())
+}
+
+/// Stop getting alerts by DM
+///
+/// Leave out the zone to unsubscribe from everything.
+#[poise::command(prefix_command, slash_command)]
+async fn unsubscribe(
+    ctx: Context<'_>,
+    #[description = "NWS zone or county code, leave out for all"] zone: Option<String>,
+) -> Result<(), Error> {
+    let zone = zone.map(|z| z.trim().to_uppercase());
+
+    let mut db = ctx.data().db.lock().await;
+    let Some(subscription) = db.subscriptions.get_mut(&ctx.author().id) else {
+        drop(db);
+        ctx.say("You aren't subscribed to any alerts").await?;
+        return Ok(());
+    };
+    let message = match zone {
+        Some(zone) if subscription.zones.remove(&zone) => format!("Unsubscribed from {zone}"),
+        Some(zone) => format!("You aren't subscribed to {zone}"),
+        None => {
+            subscription.zones.clear();
+            String::from("Unsubscribed from all alerts")
+        }
+    };
+    // Keep members' quiet hours around in case they subscribe again
+    if subscription.zones.is_empty() && subscription.quiet_hours.is_none() {
+        db.subscriptions.remove(&ctx.author().id);
+    }
+    db.save(&ctx.data().db_path).await?;
+    drop(db);
+
+    ctx.say(message).await?;
+    Ok(())
+}
+
+/// Show the alerts you get by DM
+#[poise::command(prefix_command, slash_command)]
+async fn subscriptions(ctx: Context<'_>) -> Result<(), Error> {
+    let subscription = ctx.data().db.lock().await
+        .subscriptions
+        .get(&ctx.author().id)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut message = if subscription.zones.is_empty() {
+        String::from("You aren't subscribed to any alerts")
+    } else {
+        let zones: Vec<&str> = subscription.zones.iter().map(String::as_str).collect();
+        format!("You get alerts by DM for: {}", zones.join(", "))
+    };
+    if let Some(quiet_hours) = &subscription.quiet_hours {
+        message.push_str(&format!("\nQuiet hours: {quiet_hours}"));
+    }
+
+    ctx.say(message).await?;
+    Ok(())
+}
+
+/// Hold back alert DMs at set times each day
+///
+/// Alerts that come in during quiet hours are sent together once they end.
+/// Leave out every option to turn quiet hours off.
+#[poise::command(prefix_command, slash_command, rename = "quiet-hours")]
+async fn quiet_hours(
+    ctx: Context<'_>,
+    #[description = "Start of quiet hours (ex 22:00)"] start: Option<String>,
+    #[description = "End of quiet hours (ex 07:00)"] end: Option<String>,
+    #[description = "Your timezone (ex America/Los_Angeles)"] timezone: Option<String>,
+) -> Result<(), Error> {
+    let quiet_hours = match (start, end, timezone) {
+        (None, None, None) => None,
+        (Some(start), Some(end), Some(timezone)) => {
+            let parse_time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M");
+            let (Ok(start), Ok(end)) = (parse_time(&start), parse_time(&end)) else {
+                ctx.say("Times should be 24 hour, ex 22:00").await?;
+                return Ok(());
+            };
+            let Ok(timezone) = timezone.trim().parse::<Tz>() else {
+                ctx.say(format!("Unknown timezone `{timezone}`, see the \"TZ identifier\" column here:\n\
+                    <https://en.wikipedia.org/wiki/List_of_tz_database_time_zones>")).await?;
+                return Ok(());
+            };
+            Some(QuietHours::daily(timezone, start, end))
+        }
+        _ => {
+            ctx.say("Quiet hours need a start, an end and a timezone").await?;
+            return Ok(());
+        }
+    };
+
+    let message = match &quiet_hours {
+        Some(q) => format!("Quiet hours set to {q}"),
+        None => String::from("Quiet hours turned off"),
+    };
+
+    let mut db = ctx.data().db.lock().await;
+    let subscription = db.subscriptions.entry(ctx.author().id).or_default();
+    subscription.quiet_hours = quiet_hours;
+    if subscription.zones.is_empty() && subscription.quiet_hours.is_none() {
+        db.subscriptions.remove(&ctx.author().id);
+    }
+    db.save(&ctx.data().db_path).await?;
+    drop(db);
+
+    ctx.say(message).await?;
+    Ok(())
+}
+
+/// Search past alerts
+///
+/// Every option is optional, ex `!alerts history CAC007 tornado 2026-10-01 2026-10-31`
+#[poise::command(prefix_command, slash_command, guild_only)]
+async fn history(
+    ctx: Context<'_>,
+    #[description = "NWS zone or county code (ex CAC007)"] zone: Option<String>,
+    #[description = "Part of the event name (ex Red Flag)"] event: Option<String>,
+    #[description = "Sent on or after this date (ex 2026-10-01)"] from: Option<String>,
+    #[description = "Sent on or before this date (ex 2026-10-31)"] to: Option<String>,
+) -> Result<(), Error> {
+    let parse_date = |d: Option<String>| d
+        .map(|d| NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d"))
+        .transpose();
+    let (Ok(from), Ok(to)) = (parse_date(from), parse_date(to)) else {
+        ctx.say("Dates should be year-month-day, ex 2026-10-01").await?;
+        return Ok(());
+    };
+    // Only show alerts posted in this server, and only link to messages that can be seen from it
+    let guild_id = ctx.guild_id().ok_or("Not in a server")?;
+    let channels = guild_channels(ctx);
+    let query = HistoryQuery {
+        zone: zone.map(|z| z.trim().to_uppercase()),
+        event: event.map(|e| e.trim().to_string()),
+        from,
+        to,
+        channels: Some(channels.clone()),
+    };
+
+    let entries: Vec<String> = query.search(&ctx.data().db.lock().await.history)
+        .into_iter()
+        .map(|alert| describe_archived(alert, guild_id, &channels))
+        .collect();
+    if entries.is_empty() {
+        ctx.say("No past alerts found").await?;
+        return Ok(());
+    }
+
+    send_pages(ctx, "past alert(s)", &entries).await
+}
+
+/// Show who has checked in on alerts posted in this server
+#[poise::command(prefix_command, slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn checkins(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or("Not in a server")?;
+    let channels = guild_channels(ctx);
+
+    let db = ctx.data().db.lock().await;
+    let mut found: Vec<(MessageId, &CheckIns)> = db.check_ins.iter()
+        .filter(|(_, c)| channels.contains(&c.channel_id))
+        .map(|(&message_id, c)| (message_id, c))
+        .collect();
+    // Message IDs go up over time, so this is newest first
+    found.sort_by_key(|(message_id, _)| Reverse(*message_id));
+    let entries: Vec<String> = found.into_iter()
+        .map(|(message_id, check_ins)| {
+            let message = (check_ins.channel_id, message_id);
+            // The latest version of the alert shown in the message
+            let alert = db.history.iter().rev().find(|a| a.messages.contains(&message));
+            describe_check_ins(alert, check_ins, message, guild_id)
+        })
+        .collect();
+    drop(db);
+
+    if entries.is_empty() {
+        ctx.say("Nobody has checked in on an alert yet").await?;
+        return Ok(());
+    }
+
+    send_pages(ctx, "alert(s) with check-ins", &entries).await
+}
+
+/// Channels and threads in the server a command was used in.
+fn guild_channels(ctx: Context<'_>) -> HashSet<ChannelId> {
+    ctx.guild()
+        .map(|g| g.channels.keys().copied().chain(g.threads.iter().map(|t| t.id)).collect())
+        .unwrap_or_default()
+}
+
+/// Reply with entries as an embed, split into pages of [`HISTORY_PAGE_LEN`] if needed.
+async fn send_pages(ctx: Context<'_>, noun: &str, entries: &[String]) -> Result<(), Error> {
+    let pages: Vec<String> = entries.chunks(HISTORY_PAGE_LEN)
+        .enumerate()
+        .map(|(i, page)| format!(
+            "**{} {noun}, page {} of {}**\n\n{}",
+            entries.len(),
+            i + 1,
+            entries.len().div_ceil(HISTORY_PAGE_LEN),
+            page.join("\n\n"),
+        ))
+        .collect();
+    if let [page] = &pages[..] {
+        ctx.send(CreateReply::default().embed(serenity::CreateEmbed::new().description(page))).await?;
+    } else {
+        let pages: Vec<&str> = pages.iter().map(String::as_str).collect();
+        poise::builtins::paginate(ctx, &pages).await?;
+    }
+    Ok(())
+}
+
+/// Apply an edit to the config file, then send the new config to the alerts task.
+/// Replies with `success` or the reason the edit was rejected.
+async fn update_config(
+    ctx: Context<'_>,
+    success: &str,
+    edit: impl FnOnce(&mut DocumentMut) -> Result<(), Error>,
+) -> Result<(), Error> {
+    if !ctx.data().alerts_running.load(Ordering::Relaxed) {
+        ctx.say("Alerts are not running, check config.toml").await?;
+        return Ok(());
+    }
+    let tx = &ctx.data().config_tx;
+
+    match config::edit_config(&ctx.data().config_lock, &ctx.data().http_client, edit).await {
+        Ok(new_cfg) => {
+            tx.send_replace(new_cfg);
+            ctx.say(success).await?;
+        }
+        Err(e) => {
+            ctx.say(format!("**Unable** to update config: {e}")).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Get the table of a route by its 1-based number.
+fn route_table(doc: &mut DocumentMut, route: usize) -> Result<&mut Table, Error> {
+    let routes = doc.get_mut("alerts")
+        .and_then(|a| a.get_mut("routes"))
+        .and_then(Item::as_array_of_tables_mut)
+        .ok_or("No routes in config.toml")?;
+    let len = routes.len();
+
+    route.checked_sub(1)
+        .and_then(|i| routes.get_mut(i))
+        .ok_or_else(|| format!("No route {route}, there are {len}").into())
+}
+
+/// Get a list of strings from a table, creating it if missing.
+fn string_list<'a>(table: &'a mut Table, key: &str) -> &'a mut Array {
+    if !table.get(key).is_some_and(Item::is_array) {
+        table[key] = value(Array::new());
+    }
+    table[key].as_array_mut().expect("just checked it is an array")
+}
+
+/// Replace a comma separated list, removing it on "none".
+fn set_list(table: &mut Table, key: &str, list: Option<&str>) {
+    match list {
+        None => {}
+        Some(list) if list.trim().eq_ignore_ascii_case("none") => {
+            table.remove(key);
+        }
+        Some(list) => {
+            let items: Array = list.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect();
+            table[key] = value(items);
+        }
+    }
+}
+
+/// Set a minimum severity, urgency or certainty, removing it on "any".
+/// Invalid values are caught when the edited config is parsed.
+fn set_threshold(table: &mut Table, key: &str, threshold: Option<&str>) {
+    let Some(threshold) = threshold.map(str::trim) else {
+        return;
+    };
+    if threshold.eq_ignore_ascii_case("any") {
+        table.remove(key);
+        return;
+    }
+
+    // Values are capitalized in the config (ex "Severe")
+    let mut chars = threshold.chars();
+    let capitalized: String = chars.next()
+        .map(|c| c.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect())
+        .unwrap_or_default();
+    table[key] = value(capitalized);
+}
+
+/// Sorted, comma separated list of a set's items.
+fn join_sorted<T: Debug>(set: &HashSet<T>) -> String {
+    let mut items: Vec<String> = set.iter()
+        .map(|i| format!("{i:?}").trim_matches('"').to_string())
+        .collect();
+    items.sort();
+    items.join(", ")
+}
+
+/// Short summary of a filter, ex "Fire, Met, at least Severe".
+fn describe_filter(filter: &AlertFilter) -> String {
+    let mut parts = Vec::new();
+    if !filter.alert_types.is_empty() {
+        parts.push(join_sorted(&filter.alert_types));
+    }
+    if !filter.events.is_empty() {
+        parts.push(format!("only {}", join_sorted(&filter.events)));
+    }
+    if !filter.exclude_events.is_empty() {
+        parts.push(format!("except {}", join_sorted(&filter.exclude_events)));
+    }
+    if let Some(min) = filter.min_severity {
+        parts.push(format!("at least {min:?}"));
+    }
+    if let Some(min) = filter.min_urgency {
+        parts.push(format!("at least {min:?} urgency"));
+    }
+    if let Some(min) = filter.min_certainty {
+        parts.push(format!("at least {min:?} certainty"));
+    }
+
+    if parts.is_empty() {
+        String::from("everything")
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// One line summary of an alerts provider's health.
+fn describe_health(provider: &Provider, health: &FeedHealth) -> String {
+    let last_success = match health.last_success {
+        Some(time) => format!("last checked <t:{}:R>", time.timestamp()),
+        None => String::from("not checked yet"),
+    };
+    if health.consecutive_failures == 0 {
+        return format!("{provider} is healthy, {last_success}");
+    }
+
+    let mut line = format!(
+        "{provider} has failed {} check(s) in a row, {last_success}",
+        health.consecutive_failures,
+    );
+    if let Some(error) = &health.last_error {
+        line.push_str(&format!("\nLast error: {error}"));
+    }
+    if let Some(retry_at) = health.retry_at {
+        line.push_str(&format!("\nRetrying <t:{}:R>", retry_at.timestamp()));
+    }
+    line
+}
+
+/// A few lines describing an archived alert, linking to where it was posted in a server.
+fn describe_archived(alert: &ArchivedAlert, guild_id: GuildId, channels: &HashSet<ChannelId>) -> String {
+    let mut area = alert.area_desc.clone();
+    if area.chars().count() > MAX_AREA_LEN {
+        area = area.chars().take(MAX_AREA_LEN).collect::<String>() + "…";
+    }
+    let kind = match alert.message_type {
+        MessageType::Update => " (update)",
+        MessageType::Cancel => " (cancelled)",
+        _ => "",
+    };
+
+    let mut lines = vec![
+        format!("**{}**{kind}, {:?}", alert.event, alert.severity),
+        area,
+    ];
+    let mut when = format!("<t:{}:f>", alert.sent.timestamp());
+    if let Some(end) = alert.end {
+        when.push_str(&format!(" until <t:{}:f>", end.timestamp()));
+    }
+    if !alert.areas.is_empty() {
+        when.push_str(&format!(" in {}", alert.areas.join(", ")));
+    }
+    lines.push(when);
+
+    let links: Vec<String> = alert.messages.iter()
+        .filter(|(channel_id, _)| channels.contains(channel_id))
+        .map(|(channel_id, message_id)| message_id.link(*channel_id, Some(guild_id)))
+        .collect();
+    if !links.is_empty() {
+        lines.push(links.join(" "));
+    }
+
+    lines.join("\n")
+}
+
+/// Several lines describing where a route sends alerts and which ones.
+fn describe_route(cfg: &Config, route: &AlertRoute) -> String {
+    let zones: Vec<String> = route.areas.iter().map(|a| cfg.zone_label(a)).collect();
+    let mut lines = vec![
+        format!("Channel: {}", ChannelId::new(route.channel).mention()),
+        format!("Zones: {}", zones.join(", ")),
+        format!("Filters: {}", describe_filter(&route.filter)),
+    ];
+    if !route.points.is_empty() {
+        let points: Vec<String> = route.points.iter().map(|p| p.to_string()).collect();
+        lines.push(format!("Points: {}", points.join(", ")));
+    }
+    if route.polygon.is_some() {
+        lines.push(String::from("Limited to a polygon"));
+    }
+    for feed in &route.feeds {
+        lines.push(format!("Feed: <{}>", feed.url));
+    }
+    if let Some(watch) = &route.earthquakes {
+        lines.push(format!("Earthquakes: {}", watch.key(route.earthquake_points())));
+    }
+    if let Some(watch) = &route.air_quality {
+        lines.push(format!("Air quality: {}", watch.key(route.air_quality_points())));
+    }
+    for mention in &route.mentions {
+        let mut when = Vec::new();
+        if !mention.events.is_empty() {
+            when.push(join_sorted(&mention.events));
+        }
+        if let Some(min) = mention.min_severity {
+            when.push(format!("at least {min:?}"));
+        }
+        let when = if when.is_empty() { String::from("every alert") } else { when.join(" or ") };
+        // Shown as plain ids so the status doesn't ping anyone
+        lines.push(format!("Mentions role {} on {when}", RoleId::new(mention.role)));
+    }
+    if let Some(digest) = &route.digest {
+        lines.push(format!("Digest: {digest}"));
+    }
+    if let Some(quiet_hours) = &route.quiet_hours {
+        lines.push(format!("Quiet hours: {quiet_hours}"));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// A few lines tallying the check-ins on an alert message, and who needs help.
+fn describe_check_ins(
+    alert: Option<&ArchivedAlert>,
+    check_ins: &CheckIns,
+    (channel_id, message_id): (ChannelId, MessageId),
+    guild_id: GuildId,
+) -> String {
+    let mut lines = vec![
+        match alert {
+            Some(alert) => format!("**{}**, {:?}", alert.event, alert.severity),
+            None => String::from("**Unknown alert**"),
+        },
+        message_id.link(channel_id, Some(guild_id)),
+        check_ins.tally()
+            .into_iter()
+            .map(|(check_in, count)| format!("{}: {count}", check_in.label()))
+            .collect::<Vec<String>>()
+            .join(", "),
+    ];
+    let need_help = check_ins.need_help();
+    if !need_help.is_empty() {
+        let users: Vec<String> = need_help.iter().map(|u| u.mention().to_string()).collect();
+        lines.push(format!("Needs help: {}", users.join(" ")));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Alert settings
+[alerts]
+check_interval = 60 # seconds
+exclude_events = ["Special Weather Statement"]
+
+# Butte county
+[[alerts.routes]]
+channel = 1472708201211494562
+areas = ["CAC007", "CAZ215"] # home
+min_severity = "Moderate"
+"#;
+
+    fn doc() -> DocumentMut {
+        CONFIG.parse().expect("valid toml")
+    }
+
+    #[test]
+    fn zone_edits_keep_comments_and_formatting() {
+        let mut doc = doc();
+        string_list(route_table(&mut doc, 1).unwrap(), "areas").push("CAZ278");
+        assert_eq!(doc.to_string(), CONFIG.replace(r#""CAZ215"]"#, r#""CAZ215", "CAZ278"]"#));
+
+        string_list(route_table(&mut doc, 1).unwrap(), "areas").retain(|a| a.as_str() != Some("CAC007"));
+        let areas: Vec<&str> = doc["alerts"]["routes"][0]["areas"].as_array().unwrap()
+            .iter()
+            .filter_map(|a| a.as_str())
+            .collect();
+        assert_eq!(areas, ["CAZ215", "CAZ278"]);
+        assert!(doc.to_string().starts_with("# Alert settings\n[alerts]\ncheck_interval = 60 # seconds\n"));
+        assert!(doc.to_string().contains("# Butte county\n[[alerts.routes]]\n"));
+    }
+
+    #[test]
+    fn channel_edits_only_change_the_channel() {
+        let mut doc = doc();
+        route_table(&mut doc, 1).unwrap()["channel"] = value(1472708201211494999_i64);
+        assert_eq!(doc.to_string(), CONFIG.replace("1472708201211494562", "1472708201211494999"));
+    }
+
+    #[test]
+    fn missing_lists_are_created() {
+        let mut doc = doc();
+        let table = doc["alerts"].as_table_mut().unwrap();
+        string_list(table, "areas").push("CAC007");
+        assert_eq!(doc["alerts"]["areas"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn routes_are_numbered_from_one() {
+        let mut doc = doc();
+        assert!(route_table(&mut doc, 1).is_ok());
+        let err = route_table(&mut doc, 2).unwrap_err();
+        assert_eq!(err.to_string(), "No route 2, there are 1");
+        assert!(route_table(&mut doc, 0).is_err());
+
+        let mut empty: DocumentMut = "[alerts]\ncheck_interval = 60\n".parse().unwrap();
+        assert_eq!(route_table(&mut empty, 1).unwrap_err().to_string(), "No routes in config.toml");
+    }
+
+    #[test]
+    fn filters_are_set_and_cleared() {
+        let mut doc = doc();
+        let table = route_table(&mut doc, 1).unwrap();
+        set_list(table, "events", Some("Red Flag Warning, Tornado Warning,"));
+        set_list(table, "alert_types", None);
+        set_threshold(table, "min_urgency", Some(" expected "));
+        set_threshold(table, "min_severity", Some("any"));
+
+        let route = &doc["alerts"]["routes"][0];
+        let events: Vec<&str> = route["events"].as_array().unwrap().iter().filter_map(|e| e.as_str()).collect();
+        assert_eq!(events, ["Red Flag Warning", "Tornado Warning"]);
+        assert!(route.get("alert_types").is_none());
+        assert_eq!(route["min_urgency"].as_str(), Some("Expected"));
+        assert!(route.get("min_severity").is_none());
+
+        let table = doc["alerts"].as_table_mut().unwrap();
+        set_list(table, "exclude_events", Some("none"));
+        assert!(doc["alerts"].get("exclude_events").is_none());
+        assert!(doc.to_string().contains("check_interval = 60 # seconds\n"));
+    }
+}
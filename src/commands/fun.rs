@@ -1,4 +1,4 @@
-use crate::{Context, Error};
+use crate::{Context, Error, alerts::APP_USER_AGENT, chunk::{MAX_MSG_LEN, say_chunked, truncate}};
 use chrono::Datelike;
 use poise::serenity_prelude::{GetMessages, Mention, ReactionType};
 use poise::{serenity_prelude::{self as serenity, Mentionable}, CreateReply};
@@ -63,7 +63,7 @@ pub async fn boop(ctx: Context<'_>) -> Result<(), Error> {
 
     ctx.send(reply).await?;
 
-    let timeout = std::time::Duration::from_secs(120);
+    let timeout = ctx.data().config.borrow().fun.boop_timeout;
     let mut boop_count = 0;
     while let Some(mci) = serenity::ComponentInteractionCollector::new(ctx)
         .author_id(ctx.author().id)
@@ -88,12 +88,12 @@ pub async fn boop(ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
-fn mock_helper(msg: &str) -> String {
+/// `uppercase_chance` is the chance of capitalizing each letter, from 0 to 1
+fn mock_helper(msg: &str, uppercase_chance: f64) -> String {
     let mut rng = rand::rng();
     msg.chars()
         .map(|c| {
-            // 40% chance of capitalizing letter, idk it feels better than 50-50
-            if rng.random_bool(0.4) {
+            if rng.random_bool(uppercase_chance) {
                 c.to_ascii_uppercase()
             } else {
                 c.to_ascii_lowercase()
@@ -114,9 +114,10 @@ pub async fn mock(
         None => get_last_message(&ctx).await?,
     };
 
-    let response = mock_helper(&msg.content);
+    let chance = ctx.data().config.borrow().fun.mock_uppercase_chance;
+    let response = mock_helper(&msg.content, chance);
 
-    ctx.say(response).await?;
+    say_chunked(ctx, &response).await?;
 
     Ok(())
 }
@@ -128,8 +129,9 @@ pub async fn mock_ctx_menu(
     #[description = "Message to mock"] 
     msg: serenity::Message,
 ) -> Result<(), Error> {
-    let response = mock_helper(&msg.content);
-    ctx.say(response).await?;
+    let chance = ctx.data().config.borrow().fun.mock_uppercase_chance;
+    let response = mock_helper(&msg.content, chance);
+    say_chunked(ctx, &response).await?;
     Ok(())
 }
 
@@ -202,7 +204,7 @@ pub async fn roll(
         }
     );
 
-    ctx.say(format!("{}\n\nSum of all rolls: {}", message, sum)).await?;
+    say_chunked(ctx, &format!("{}\n\nSum of all rolls: {}", message, sum)).await?;
 
     Ok(())
 }
@@ -223,7 +225,7 @@ pub async fn wiki(
     let client = Client::new();
     let response: Value = client
         .get(req)
-        .header(USER_AGENT, "rust-web-api-client")
+        .header(USER_AGENT, APP_USER_AGENT)
         .send()
         .await?
         .json()
@@ -242,10 +244,8 @@ pub async fn wiki(
                 }
                 // If the message (and some formatting) is longer than 2000 chars,
                 // truncate it
-                else if title.len() + extract.len() + 6 > 2000 {
-                    format!("**{title}:**\n{}...", &extract[..(2000 - title.len() - 9)])
-                } else {
-                    format!("**{title}:**\n{}", &extract)
+                else {
+                    truncate(&format!("**{title}:**\n{extract}"), MAX_MSG_LEN)
                 }
             })
         })
@@ -264,7 +264,7 @@ pub async fn say(
     #[description = "Message to say"]
     msg: String,
 ) -> Result<(), Error> {
-    ctx.say(msg).await?;
+    say_chunked(ctx, &msg).await?;
     Ok(())
 }
 
@@ -535,7 +535,7 @@ async fn owo_helper(
         ctx.say("UwU dis usew is immuwune, sowwy! 😭").await?;
     } else {
         let transformed = owofy(&msg.content);
-        ctx.say(&transformed).await?;
+        say_chunked(ctx, &transformed).await?;
     }
     
     Ok(())
@@ -550,14 +550,17 @@ enum FactType {
 async fn fact_helper(ctx: Context<'_>, ftype: FactType) -> Result<(), Error> {
     let client = Client::new();
 
-    let url = match ftype {
-        FactType::Regular => "https://raw.githubusercontent.com/thomasdevine01/hackbot-functions/main/fact.json",
-        FactType::Catfact => "https://raw.githubusercontent.com/vadimdemedes/cat-facts/master/cat-facts.json",
+    let url = {
+        let config = ctx.data().config.borrow();
+        match ftype {
+            FactType::Regular => config.fun.fact_url.clone(),
+            FactType::Catfact => config.fun.cat_fact_url.clone(),
+        }
     };
 
     let response: Value = client
         .get(url)
-        .header(USER_AGENT, "rust-web-api-client")
+        .header(USER_AGENT, APP_USER_AGENT)
         .send()
         .await?
         .json()
@@ -1,4 +1,4 @@
-use crate::{Context, Error};
+use crate::{Context, Error, chunk::say_chunked};
 use poise::serenity_prelude::{self as serenity, Mentionable};
 use std::process::Command;
 
@@ -47,7 +47,7 @@ pub async fn vote(
     };
 
     let response = format!("Successfully voted for {choice}. {choice} now has {num_votes} votes!");
-    ctx.say(response).await?;
+    say_chunked(ctx, &response).await?;
     Ok(())
 }
 
@@ -70,18 +70,18 @@ pub async fn getvotes(
             0 => format!("Nobody has voted for {} yet", choice),
             _ => format!("{} people have voted for {}", num_votes, choice),
         };
-        ctx.say(response).await?;
+        say_chunked(ctx, &response).await?;
     } else {
         let mut response = String::new();
         for (choice, num_votes) in ctx.data().votes.lock().await.iter() {
-            response += &format!("{}: {} votes", choice, num_votes);
+            response += &format!("{}: {} votes\n", choice, num_votes);
         }
 
         if response.is_empty() {
             response += "Nobody has voted for anything yet :(";
         }
 
-        ctx.say(response).await?;
+        say_chunked(ctx, &response).await?;
     };
 
     Ok(())
@@ -94,7 +94,7 @@ pub async fn echo(
     ctx: Context<'_>,
     #[description = "Message to echo (enter a link or ID)"] msg: serenity::Message,
 ) -> Result<(), Error> {
-    ctx.say(&msg.content).await?;
+    say_chunked(ctx, &msg.content).await?;
     Ok(())
 }
 
@@ -1,3 +1,5 @@
+pub mod alerts;
 pub mod fun;
 pub mod info;
 pub mod roles;
+pub mod weather;
@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
-use crate::{Context, Error};
+use crate::{Context, Error, chunk::say_chunked};
 use levenshtein::levenshtein;
 use poise::{serenity_prelude::{EditRole, Role, RoleId}};
 
@@ -12,11 +12,7 @@ pub async fn list_roles(ctx: Context<'_>) -> Result<(), Error> {
     let roles = guild_id.roles(&ctx).await?;
 
     // Some roles to hide
-    let hidden_roles = [
-        String::from("@everyone"),
-        String::from("Moderator"),
-        String::from("SerenityBot"),
-    ];
+    let hidden_roles = ctx.data().config.borrow().roles.hidden_roles.clone();
 
     // Convert roles hashmap into vector of strings, ignore some roles
     let mut roles: Vec<String> = roles.iter()
@@ -34,7 +30,7 @@ pub async fn list_roles(ctx: Context<'_>) -> Result<(), Error> {
     roles.sort();
     let roles_str = format!("Server roles:\n- {}", roles.join("\n- "));
 
-    ctx.say(roles_str).await?;
+    say_chunked(ctx, &roles_str).await?;
 
     Ok(())
 }
@@ -153,10 +149,10 @@ pub async fn add(
     }
 
     if !unsuccessful_roles.is_empty() {
-        ctx.say(format!("**Unable** to add:\n{}", unsuccessful_roles)).await?;
+        say_chunked(ctx, &format!("**Unable** to add:\n{}", unsuccessful_roles)).await?;
     }
     if !added_roles.is_empty() {
-        ctx.say(format!("**Successfully** added:\n{}", added_roles)).await?;
+        say_chunked(ctx, &format!("**Successfully** added:\n{}", added_roles)).await?;
     }
 
     Ok(())
@@ -193,10 +189,10 @@ pub async fn del(
     }
 
     if !unsuccessful_roles.is_empty() {
-        ctx.say(format!("**Unable** to delete:\n{}", unsuccessful_roles)).await?;
+        say_chunked(ctx, &format!("**Unable** to delete:\n{}", unsuccessful_roles)).await?;
     }
     if !deleted_roles.is_empty() {
-        ctx.say(format!("**Successfully** deleted:\n{}", deleted_roles)).await?;
+        say_chunked(ctx, &format!("**Successfully** deleted:\n{}", deleted_roles)).await?;
     }
 
     Ok(())
@@ -241,7 +237,7 @@ pub async fn create_roles(
         created_roles.push(' ');
     }
 
-    ctx.say(format!("Successfully created roles: {created_roles}")).await?;
+    say_chunked(ctx, &format!("Successfully created roles: {created_roles}")).await?;
 
     Ok(())
 }
@@ -263,7 +259,7 @@ pub async fn my_roles(
         String::from("You haven't added any roles yet!")
     };
 
-    ctx.say(message).await?;
+    say_chunked(ctx, &message).await?;
 
     Ok(())
 }
@@ -0,0 +1,140 @@
+//! On demand weather lookups from the NWS API.
+
+use crate::{
+    Context, Error,
+    alerts::{AlertSource, AlertStatus, NwsSource, Point, alert_embed, forecast_embed, is_zone_code},
+};
+use poise::CreateReply;
+
+/// Most alerts shown in reply to one lookup
+const MAX_SHOWN: usize = 5;
+
+/// Where to look up the weather for.
+enum Location {
+    /// NWS zone or county code, ex "CAC007"
+    Zone(String),
+    Point(f64, f64),
+}
+
+impl Location {
+    /// Parse either a zone code or a "lat,lon" pair.
+    fn parse(input: &str) -> Result<Self, Error> {
+        let input = input.trim();
+        let coords: Vec<&str> = input.split([',', ' ']).filter(|s| !s.is_empty()).collect();
+        if let [lat, lon] = coords[..]
+            && let (Ok(lat), Ok(lon)) = (lat.parse::<f64>(), lon.parse::<f64>()) {
+            if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+                return Err(format!("{lat},{lon} isn't a valid latitude and longitude").into());
+            }
+            return Ok(Location::Point(lat, lon));
+        }
+
+        let zone = input.to_uppercase();
+        if is_zone_code(&zone) {
+            return Ok(Location::Zone(zone));
+        }
+        Err(format!("Expected a zone code (ex CAC007) or a latitude and longitude (ex 39.73,-121.84), not `{input}`").into())
+    }
+}
+
+/// NWS client sharing the bot's HTTP client and the configured API root.
+pub fn nws(ctx: Context<'_>) -> NwsSource {
+    let base_url = ctx.data().config.borrow().alerts.nws_url.clone();
+    NwsSource::new(ctx.data().http_client.clone(), &base_url)
+}
+
+/// Look up the weather
+#[poise::command(prefix_command, slash_command, subcommands("alerts", "forecast"), subcommand_required)]
+pub async fn weather(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the active weather alerts for a zone or point
+///
+/// Enter `!weather alerts CAC007` or `!weather alerts 39.73,-121.84`
+#[poise::command(prefix_command, slash_command)]
+async fn alerts(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "Zone or county code (ex CAC007), or latitude,longitude"]
+    location: String,
+) -> Result<(), Error> {
+    let location = match Location::parse(&location) {
+        Ok(location) => location,
+        Err(e) => {
+            ctx.say(e.to_string()).await?;
+            return Ok(());
+        }
+    };
+    ctx.defer().await?;
+
+    let nws = nws(ctx);
+    let (place, alerts) = match location {
+        Location::Zone(zone) => (zone.clone(), nws.fetch_active(&zone).await),
+        Location::Point(lat, lon) => (format!("{lat},{lon}"), nws.fetch_active_at(Point { lat, lon }).await),
+    };
+    let mut alerts = match alerts {
+        Ok(alerts) => alerts,
+        Err(e) => {
+            ctx.say(format!("**Unable** to get alerts for {place}: {e}")).await?;
+            return Ok(());
+        }
+    };
+
+    if alerts.is_empty() {
+        ctx.say(format!("No active alerts for {place}")).await?;
+        return Ok(());
+    }
+
+    // Most severe first, then newest
+    alerts.sort_by(|a, b| b.severity.cmp(&a.severity).then(b.sent.cmp(&a.sent)));
+    let plural = if alerts.len() == 1 { "" } else { "s" };
+    let mut header = format!("**{} active alert{plural} for {place}**", alerts.len());
+    if alerts.len() > MAX_SHOWN {
+        header.push_str(&format!(" (showing the {MAX_SHOWN} most severe)"));
+    }
+    ctx.say(header).await?;
+
+    // One alert per message, as all embeds in a message share one size limit
+    for alert in alerts.iter().take(MAX_SHOWN) {
+        ctx.send(CreateReply::default().embed(alert_embed(alert, AlertStatus::New))).await?;
+    }
+
+    Ok(())
+}
+
+/// Show the forecast for a point
+///
+/// Enter `!weather forecast 39.73,-121.84`
+#[poise::command(prefix_command, slash_command)]
+async fn forecast(
+    ctx: Context<'_>,
+    #[rest]
+    #[description = "Latitude,longitude (ex 39.73,-121.84)"]
+    location: String,
+) -> Result<(), Error> {
+    let (lat, lon) = match Location::parse(&location) {
+        Ok(Location::Point(lat, lon)) => (lat, lon),
+        Ok(Location::Zone(_)) => {
+            ctx.say("Forecasts need a latitude and longitude (ex 39.73,-121.84)").await?;
+            return Ok(());
+        }
+        Err(e) => {
+            ctx.say(e.to_string()).await?;
+            return Ok(());
+        }
+    };
+    ctx.defer().await?;
+
+    match nws(ctx).fetch_forecast(Point { lat, lon }).await {
+        Ok(forecast) => {
+            let place = forecast.location.clone().unwrap_or_else(|| format!("{lat},{lon}"));
+            ctx.send(CreateReply::default().embed(forecast_embed(&forecast, &place))).await?;
+        }
+        Err(e) => {
+            ctx.say(format!("**Unable** to get the forecast for {lat},{lon}: {e}")).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! Settings for the bot itself and its non-alert commands.
+//!
+//! Every section and key is optional, anything left out keeps the
+//! bot's usual behaviour. Settings are read each time they are used,
+//! so changes apply on reload unless noted otherwise.
+
+use serde::Deserialize;
+use serde_with::{DurationSeconds, serde_as};
+use std::time::Duration;
+
+/// TOML key `[bot]`
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct BotConfig {
+    /// Prefix for text commands (ex "!")
+    pub prefix: String,
+    /// Other ways text commands can start (ex "hey bot,"). The longest match is used.
+    pub additional_prefixes: Vec<String>,
+    /// How long editing a command message re-runs the command, in seconds.
+    /// Only applied on restart.
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub edit_tracker_window: Duration,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            prefix: String::from("!"),
+            additional_prefixes: vec![String::from("hey bot,"), String::from("hey bot")],
+            edit_tracker_window: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl BotConfig {
+    /// Split a message into the prefix it starts with and the rest,
+    /// trying the longest prefixes first so "hey bot," wins over "hey bot".
+    pub fn strip_prefix<'a>(&self, content: &'a str) -> Option<(&'a str, &'a str)> {
+        let mut prefixes: Vec<&str> = std::iter::once(self.prefix.as_str())
+            .chain(self.additional_prefixes.iter().map(String::as_str))
+            .filter(|p| !p.is_empty())
+            .collect();
+        prefixes.sort_by_key(|p| std::cmp::Reverse(p.len()));
+        prefixes.into_iter()
+            .find(|p| content.starts_with(p))
+            .map(|p| content.split_at(p.len()))
+    }
+}
+
+/// TOML key `[roles]`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RolesConfig {
+    /// Roles left out of `/list_roles` (ex "Moderator")
+    pub hidden_roles: Vec<String>,
+}
+
+impl Default for RolesConfig {
+    fn default() -> Self {
+        Self {
+            hidden_roles: vec![
+                String::from("@everyone"),
+                String::from("Moderator"),
+                String::from("SerenityBot"),
+            ],
+        }
+    }
+}
+
+/// TOML key `[fun]`
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FunConfig {
+    /// JSON array of strings that `/fact` picks from
+    pub fact_url: String,
+    /// JSON array of strings that `/catfact` picks from
+    pub cat_fact_url: String,
+    /// How long `/boop` keeps counting boops, in seconds
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub boop_timeout: Duration,
+    /// Chance of each letter being capitalised by `/mock`, from 0 to 1
+    pub mock_uppercase_chance: f64,
+}
+
+impl Default for FunConfig {
+    fn default() -> Self {
+        Self {
+            fact_url: String::from("https://raw.githubusercontent.com/thomasdevine01/hackbot-functions/main/fact.json"),
+            cat_fact_url: String::from("https://raw.githubusercontent.com/vadimdemedes/cat-facts/master/cat-facts.json"),
+            boop_timeout: Duration::from_secs(120),
+            // idk it feels better than 50-50
+            mock_uppercase_chance: 0.4,
+        }
+    }
+}
+
+/// TOML key `[storage]`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Where the db file is kept, relative to the bot. Only applied on restart.
+    pub db_path: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { db_path: String::from("db.json") }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn longest_prefix_wins() {
+        let bot = BotConfig::default();
+        assert_eq!(bot.strip_prefix("hey bot, ping"), Some(("hey bot,", " ping")));
+        assert_eq!(bot.strip_prefix("hey bot ping"), Some(("hey bot", " ping")));
+        assert_eq!(bot.strip_prefix("!ping"), Some(("!", "ping")));
+        assert_eq!(bot.strip_prefix("ping"), None);
+    }
+
+    #[test]
+    fn left_out_keys_keep_their_defaults() {
+        let bot: BotConfig = toml::from_str(r#"prefix = "?""#).unwrap();
+        assert_eq!(bot.strip_prefix("?ping"), Some(("?", "ping")));
+        assert_eq!(bot.strip_prefix("hey bot, ping"), Some(("hey bot,", " ping")));
+        assert_eq!(bot.edit_tracker_window, Duration::from_secs(3600));
+    }
+}
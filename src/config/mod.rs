@@ -0,0 +1,758 @@
+//! Defines the configuration of the bot.
+//!
+//! Structs here represent the keys and values of the TOML
+//! file used to configure the bot. If no TOML is found,
+//! or it is not able to be parsed, the bot will run with
+//! default settings and without sending alerts.
+//!
+//! The config file must be named 'config.toml' and must
+//! be placed in the same directory as the bot.
+//! Run the bot with `--check-config` to list every problem
+//! with the file, without connecting to Discord.
+
+mod bot;
+mod validate;
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    path::Path,
+    time::Duration,
+};
+
+use crate::{
+    Error,
+    alerts::{Category, Certainty, Geometry, NwsSource, Point, Severity, Urgency},
+};
+use chrono::{DateTime, Datelike, NaiveTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use notify::{Watcher, RecursiveMode, RecommendedWatcher};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_with::{DurationSeconds, serde_as};
+use tokio::sync::{Mutex, watch::Sender};
+use toml_edit::DocumentMut;
+use tracing::{info, warn};
+
+pub use bot::{BotConfig, FunConfig, RolesConfig, StorageConfig};
+pub use validate::ConfigError;
+
+/// Where the config is read from, relative to the bot
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Main bot configuration struct.
+///
+/// `config.toml` keys follow the variable names
+/// in the struct.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub bot: BotConfig,
+    #[serde(default)]
+    pub roles: RolesConfig,
+    #[serde(default)]
+    pub fun: FunConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Without `[alerts]`, only DM subscriptions get alerts
+    #[serde(default)]
+    pub alerts: AlertConfig,
+    pub quiet_hours: Option<QuietHours>,
+    /// Names of the zones in `alerts`, looked up from NWS
+    #[serde(skip)]
+    pub zone_names: HashMap<String, String>,
+}
+
+/// TOML key `[alerts]`
+/// Used to configure how often to check for new alerts,
+/// which alerts are wanted and where each kind of alert is sent.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub struct AlertConfig {
+    #[serde_as(as = "DurationSeconds<u64>")]
+    pub check_interval: Duration,
+    /// Root of the NWS API, only worth changing to point at a mirror or test server
+    #[serde(default = "default_nws_url")]
+    pub nws_url: String,
+    /// USGS GeoJSON summary feed that earthquakes are read from
+    #[serde(default = "default_usgs_url")]
+    pub usgs_url: String,
+    /// Root of the AirNow API that air quality is read from
+    #[serde(default = "default_airnow_url")]
+    pub airnow_url: String,
+    /// Applied to every alert before it is matched against routes
+    #[serde(flatten)]
+    pub filter: AlertFilter,
+    pub routes: Vec<AlertRoute>,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(60),
+            nws_url: default_nws_url(),
+            usgs_url: default_usgs_url(),
+            airnow_url: default_airnow_url(),
+            filter: AlertFilter::default(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+/// Used when `nws_url` is left out
+fn default_nws_url() -> String {
+    String::from("https://api.weather.gov")
+}
+
+/// Used when `usgs_url` is left out. Every earthquake in the past hour,
+/// see https://earthquake.usgs.gov/earthquakes/feed/v1.0/geojson.php
+fn default_usgs_url() -> String {
+    String::from("https://earthquake.usgs.gov/earthquakes/feed/v1.0/summary/all_hour.geojson")
+}
+
+/// Used when `airnow_url` is left out
+fn default_airnow_url() -> String {
+    String::from("https://www.airnowapi.org")
+}
+
+/// TOML key `[[alerts.routes]]`
+/// Sends NWS alerts for a set of areas to a channel, optionally
+/// filtered further and mentioning roles.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AlertRoute {
+    pub channel: u64,
+    #[serde(default)]
+    pub areas: Vec<String>,
+    /// Places to get alerts for (ex [{ lat = 39.73, lon = -121.84 }]).
+    /// Polygon based alerts in `areas` must cover one of them.
+    #[serde(default)]
+    pub points: Vec<Point>,
+    /// GeoJSON polygon that polygon based alerts in `areas` must overlap
+    pub polygon: Option<Geometry>,
+    /// CAP feeds to get alerts from, for places NWS doesn't cover
+    #[serde(default)]
+    pub feeds: Vec<CapFeed>,
+    /// Earthquakes near the route's points, from USGS
+    pub earthquakes: Option<EarthquakeWatch>,
+    /// Poor air quality near the route's points, from AirNow
+    pub air_quality: Option<AirQualityWatch>,
+    #[serde(flatten)]
+    pub filter: AlertFilter,
+    #[serde(default)]
+    pub mentions: Vec<AlertMention>,
+    /// Replaces the global `[quiet_hours]` for this route
+    pub quiet_hours: Option<QuietHours>,
+    /// Post the route's alerts as one summary at set times instead of as they come in
+    pub digest: Option<DigestSchedule>,
+}
+
+/// TOML key `[[alerts.routes.feeds]]`
+/// A CAP 1.2 alert feed, either an Atom feed linking to CAP
+/// documents (ex MeteoAlarm) or a single CAP document.
+#[derive(Clone, Debug, Deserialize)]
+pub struct CapFeed {
+    pub url: String,
+    /// Language to show alerts in when they come in several, ex "de".
+    /// Defaults to English, then whichever language comes first.
+    pub language: Option<String>,
+}
+
+/// TOML key `[alerts.routes.earthquakes]`
+/// Alerts for earthquakes of at least `min_magnitude`
+/// within `max_distance_km` of any of `points`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct EarthquakeWatch {
+    #[serde(default = "default_min_magnitude")]
+    pub min_magnitude: f64,
+    #[serde(default = "default_max_distance_km")]
+    pub max_distance_km: f64,
+    /// Places to measure distance from. Defaults to the route's points,
+    /// set these instead to leave NWS out for places outside the US.
+    #[serde(default)]
+    pub points: Vec<Point>,
+}
+
+fn default_min_magnitude() -> f64 {
+    4.0
+}
+
+fn default_max_distance_km() -> f64 {
+    100.0
+}
+
+/// TOML key `[alerts.routes.air_quality]`
+/// Alerts when the AQI of any pollutant reported near any of `points`
+/// reaches `min_aqi`. Needs an API key from https://docs.airnowapi.org
+/// in the `AIRNOW_API_KEY` environment variable, and is skipped without one.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AirQualityWatch {
+    /// Defaults to 101, where air becomes unhealthy for sensitive groups
+    #[serde(default = "default_min_aqi")]
+    pub min_aqi: i32,
+    /// How far from each point to look for a reporting area, in miles
+    #[serde(default = "default_aqi_distance_miles")]
+    pub distance_miles: u32,
+    /// Places to check. Defaults to the route's points.
+    #[serde(default)]
+    pub points: Vec<Point>,
+}
+
+fn default_min_aqi() -> i32 {
+    101
+}
+
+fn default_aqi_distance_miles() -> u32 {
+    25
+}
+
+/// TOML key `[[alerts.routes.mentions]]`
+/// Mentions a role when an alert is one of `events` or is at least
+/// `min_severity`. With neither set, every alert mentions the role.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AlertMention {
+    pub role: u64,
+    #[serde(default)]
+    pub events: HashSet<String>,
+    pub min_severity: Option<Severity>,
+}
+
+/// Filters accepted by both `[alerts]` and `[[alerts.routes]]`.
+/// Any filter left out allows everything.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct AlertFilter {
+    /// CAP categories to allow (ex "Met", "Fire")
+    #[serde(default)]
+    pub alert_types: HashSet<Category>,
+    /// Event names to allow (ex "Red Flag Warning")
+    #[serde(default)]
+    pub events: HashSet<String>,
+    /// Event names to always drop (ex "Special Weather Statement")
+    #[serde(default)]
+    pub exclude_events: HashSet<String>,
+    pub min_severity: Option<Severity>,
+    pub min_urgency: Option<Urgency>,
+    pub min_certainty: Option<Certainty>,
+}
+
+/// TOML key `[quiet_hours]`, or `[alerts.routes.quiet_hours]` for a single route.
+/// Schedule times that the bot holds back new alerts.
+/// Held alerts are sent as a digest once quiet hours end.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "QuietHoursToml")]
+pub struct QuietHours {
+    /// IANA timezone the windows are in (ex "America/Los_Angeles")
+    timezone: Tz,
+    windows: Vec<QuietWindow>,
+    /// Alerts at least this severe are sent even during quiet hours
+    override_severity: Option<Severity>,
+}
+
+/// Quiet hours as written in config.toml, which can also be the older
+/// `start` and `end` keys for a single window every day.
+#[derive(Deserialize)]
+struct QuietHoursToml {
+    timezone: Option<Tz>,
+    #[serde(default)]
+    windows: Vec<QuietWindow>,
+    start: Option<NaiveTime>,
+    end: Option<NaiveTime>,
+    override_severity: Option<Severity>,
+}
+
+impl TryFrom<QuietHoursToml> for QuietHours {
+    type Error = String;
+
+    fn try_from(toml: QuietHoursToml) -> Result<Self, Self::Error> {
+        let mut windows = toml.windows;
+        let old_form = toml.start.is_some() || toml.end.is_some();
+        match (toml.start, toml.end) {
+            (None, None) => {}
+            (Some(_), Some(_)) if !windows.is_empty() => {
+                return Err(String::from("use either `start` and `end` or `windows`, not both"));
+            }
+            (Some(start), Some(end)) => windows.push(QuietWindow { start, end, days: HashSet::new() }),
+            _ => return Err(String::from("`start` and `end` need to be set together")),
+        }
+
+        let Some(timezone) = toml.timezone else {
+            return Err(if old_form {
+                String::from("quiet hours need a `timezone` for `start` and `end` (ex timezone = \"America/Los_Angeles\")")
+            } else {
+                String::from("missing field `timezone`")
+            });
+        };
+        Ok(Self { timezone, windows, override_severity: toml.override_severity })
+    }
+}
+
+/// TOML key `[[quiet_hours.windows]]`
+/// A single stretch of quiet time, optionally limited to certain days.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct QuietWindow {
+    start: NaiveTime,
+    end: NaiveTime,
+    /// Days the window starts on (ex ["Sat", "Sun"]). Every day if left out.
+    #[serde(default)]
+    days: HashSet<Weekday>,
+}
+
+/// Check if a day is in a set of days, where an empty set means every day.
+fn on_day(days: &HashSet<Weekday>, day: Weekday) -> bool {
+    days.is_empty() || days.contains(&day)
+}
+
+/// Format a set of days Monday first, ex "Sat, Sun".
+fn format_days(days: &HashSet<Weekday>) -> String {
+    let mut days: Vec<Weekday> = days.iter().copied().collect();
+    days.sort_by_key(Weekday::num_days_from_monday);
+    let days: Vec<String> = days.iter().map(Weekday::to_string).collect();
+    days.join(", ")
+}
+
+impl QuietWindow {
+    /// Check if a local day and time falls within the window.
+    fn contains(&self, day: Weekday, time: NaiveTime) -> bool {
+        let starts_on = |day: Weekday| on_day(&self.days, day);
+
+        if self.start <= self.end {
+            // Same-day
+            starts_on(day) && time >= self.start && time < self.end
+        } else {
+            // Overnight (ex 22:00–07:00), the early hours belong to the previous day's window
+            (starts_on(day) && time >= self.start)
+                || (starts_on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+impl QuietHours {
+    /// Quiet hours with a single window that applies every day.
+    pub fn daily(timezone: Tz, start: NaiveTime, end: NaiveTime) -> Self {
+        let window = QuietWindow { start, end, days: HashSet::new() };
+        Self { timezone, windows: vec![window], override_severity: None }
+    }
+
+    /// Check if an alert of the given severity ignores quiet hours.
+    pub fn overridden_by(&self, severity: Severity) -> bool {
+        self.override_severity.is_some_and(|min| severity >= min)
+    }
+
+    /// Check if the given time is within quiet hours.
+    pub fn is_quiet(&self, now: DateTime<Utc>) -> bool {
+        let local = now.with_timezone(&self.timezone);
+        self.windows.iter().any(|w| w.contains(local.weekday(), local.time()))
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let windows: Vec<String> = self.windows.iter()
+            .map(|w| {
+                let mut window = format!("{}–{}", w.start.format("%H:%M"), w.end.format("%H:%M"));
+                if !w.days.is_empty() {
+                    window.push_str(&format!(" ({})", format_days(&w.days)));
+                }
+                window
+            })
+            .collect();
+        write!(f, "{} {}", windows.join(", "), self.timezone)?;
+        if let Some(severity) = self.override_severity {
+            write!(f, ", {severity:?} alerts override")?;
+        }
+        Ok(())
+    }
+}
+
+/// TOML key `[alerts.routes.digest]`
+/// Times that a route in digest mode posts the alerts it collected since the last digest.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DigestSchedule {
+    /// IANA timezone the times are in (ex "America/Los_Angeles")
+    timezone: Tz,
+    /// Local times to post at (ex ["07:00", "19:00"])
+    times: Vec<NaiveTime>,
+    /// Days to post on (ex ["Mon"] for weekly). Every day if left out.
+    #[serde(default)]
+    days: HashSet<Weekday>,
+}
+
+impl DigestSchedule {
+    /// The first time a digest is due after the given time.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut times = self.times.clone();
+        times.sort();
+        let start = after.with_timezone(&self.timezone).date_naive();
+        // A week and a day covers every day a digest can be on
+        start.iter_days()
+            .take(8)
+            .filter(|date| on_day(&self.days, date.weekday()))
+            .flat_map(|date| times.iter().map(move |&time| date.and_time(time)))
+            .filter_map(|local| {
+                // Times skipped by a DST change are posted an hour later
+                self.timezone.from_local_datetime(&local).earliest()
+                    .or_else(|| self.timezone.from_local_datetime(&(local + TimeDelta::hours(1))).earliest())
+            })
+            .map(|time| time.with_timezone(&Utc))
+            .find(|&time| time > after)
+    }
+}
+
+impl fmt::Display for DigestSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut times = self.times.clone();
+        times.sort();
+        let times: Vec<String> = times.iter().map(|t| t.format("%H:%M").to_string()).collect();
+        write!(f, "{} {}", times.join(", "), self.timezone)?;
+        if !self.days.is_empty() {
+            write!(f, " ({})", format_days(&self.days))?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Look up the name of every zone in the routes, rejecting any that NWS
+    /// doesn't know about. If NWS can't be reached the zones are left unchecked.
+    /// Problems don't have lines until [`ConfigError::locate`] is given the file.
+    pub async fn resolve_zone_names(&mut self, client: &Client) -> Result<(), ConfigError> {
+        let zones: BTreeSet<&str> = self.alerts.routes.iter()
+            .flat_map(|r| &r.areas)
+            .map(String::as_str)
+            .collect();
+        let zones: Vec<&str> = zones.into_iter().collect();
+
+        let source = NwsSource::new(client.clone(), &self.alerts.nws_url);
+        let names = match source.fetch_zone_names(&zones).await {
+            Ok(names) => names,
+            Err(e) => {
+                warn!("Unable to look up zone names, leaving zones unchecked: {e}");
+                return Ok(());
+            }
+        };
+
+        self.check_zones_known(&names)?;
+        self.zone_names = names;
+        Ok(())
+    }
+
+    /// Zone code along with its name if known, ex "CAC007 (Butte, CA)".
+    pub fn zone_label(&self, zone: &str) -> String {
+        match self.zone_names.get(zone) {
+            Some(name) => format!("{zone} ({name})"),
+            None => zone.to_string(),
+        }
+    }
+}
+
+/// Parse and validate the text of the config file.
+pub fn parse_config(source: &str) -> Result<Config, ConfigError> {
+    let config: Config = toml::from_str(source).map_err(|e| ConfigError::from_toml(&e, source))?;
+    config.validate().map_err(|e| e.locate(source))?;
+    Ok(config)
+}
+
+/// Load the configuration file from the relative path 'config.toml',
+/// then check its zones with NWS.
+pub async fn load_resolved_config(client: &Client) -> Result<Config, ConfigError> {
+    let config_str = fs::read_to_string(CONFIG_FILE).map_err(ConfigError::unreadable)?;
+    let mut config = parse_config(&config_str)?;
+
+    #[cfg(debug_assertions)] {
+        println!("Alerts: {:?}", config.alerts);
+        println!("Quiet hours: {:?}", config.quiet_hours);
+    }
+
+    config.resolve_zone_names(client).await.map_err(|e| e.locate(&config_str))?;
+    Ok(config)
+}
+
+/// Apply an edit to 'config.toml', keeping its comments and formatting.
+/// The file is only written if the edited config is still valid.
+/// `lock` is held from reading the file to writing it, so edits made
+/// at the same time don't overwrite each other.
+pub async fn edit_config(
+    lock: &Mutex<()>,
+    client: &Client,
+    edit: impl FnOnce(&mut DocumentMut) -> Result<(), Error>,
+) -> Result<Config, Error> {
+    let _guard = lock.lock().await;
+    let mut doc: DocumentMut = tokio::fs::read_to_string(CONFIG_FILE).await?.parse()?;
+    edit(&mut doc)?;
+
+    let new_str = doc.to_string();
+    let mut config = parse_config(&new_str)?;
+    config.resolve_zone_names(client).await.map_err(|e| e.locate(&new_str))?;
+    tokio::fs::write(CONFIG_FILE, new_str).await?;
+
+    Ok(config)
+}
+
+/// Runs in the background watching for config file changes.
+pub async fn watch_config(tx: Sender<Config>, client: Client)
+-> Result<(), Box<dyn std::error::Error>> {
+    let (notify_tx, mut notify_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let mut watcher = RecommendedWatcher::new(
+        move |res| {
+            if let Ok(event) = res {
+                let _ = notify_tx.send(event);
+            }
+        },
+        notify::Config::default(),
+    )?;
+    
+    watcher.watch(Path::new("."), RecursiveMode::NonRecursive)?;
+    info!("Watching config.toml for changes");
+    
+    loop {
+        tokio::select! {
+            Some(event) = notify_rx.recv() => {
+                if event.paths.iter().any(|p| p.ends_with(CONFIG_FILE)) 
+                    && event.kind.is_modify() {
+                    #[cfg(debug_assertions)]
+                    println!("Config file changed: {:?}", event);
+
+                    // Try to debounce config changes a little? fuck
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    
+                    match load_resolved_config(&client).await {
+                        Ok(new_cfg) => {
+                            if let Err(e) = tx.send(new_cfg) {
+                                warn!("Unable to send new config: {}", e);
+                            } else {
+                                info!("Config reloaded successfully");
+                            }
+                        }
+                        Err(e) => warn!("Invalid config.toml, keeping old config:\n{}", e)
+                    }
+                }
+            }
+        }
+
+        // Try to debounce a bit?
+        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quiet_hours(toml: &str) -> QuietHours {
+        toml::from_str(toml).expect("valid quiet hours")
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().expect("valid timestamp")
+    }
+
+    const OVERNIGHT: &str = r#"
+        timezone = "America/Los_Angeles"
+        [[windows]]
+        start = "22:00"
+        end = "07:00"
+    "#;
+
+    fn config(routes: &str) -> Config {
+        toml::from_str(&format!("[alerts]\ncheck_interval = 60\n{routes}")).expect("valid config")
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let cfg = parse_config("").expect("valid config");
+        assert!(cfg.alerts.routes.is_empty());
+        assert_eq!(cfg.alerts.check_interval, Duration::from_secs(60));
+        assert_eq!(cfg.bot.prefix, "!");
+        assert_eq!(cfg.storage.db_path, "db.json");
+    }
+
+    #[test]
+    fn invalid_zone_codes_are_config_errors() {
+        let cfg = config(r#"
+            [[alerts.routes]]
+            channel = 1
+            areas = ["CAC007", "CA007", "XXC007", "cac007", "PZZ530"]
+        "#);
+        let err = cfg.validate().expect_err("invalid zones").to_string();
+        assert!(err.contains("`CA007`"));
+        assert!(err.contains("`XXC007`"));
+        assert!(err.contains("`cac007`"));
+        assert!(!err.contains("`CAC007`"));
+        assert!(!err.contains("`PZZ530`"));
+    }
+
+    #[test]
+    fn routes_need_somewhere_to_watch() {
+        let cfg = config(r#"
+            [[alerts.routes]]
+            channel = 1
+            areas = ["CAC007"]
+            [[alerts.routes]]
+            channel = 2
+        "#);
+        let err = cfg.validate().expect_err("empty route").to_string();
+        assert!(err.contains("alerts.routes[1]: needs areas"));
+        assert!(!err.contains("alerts.routes[0]: needs areas"));
+    }
+
+    #[test]
+    fn earthquakes_need_points() {
+        let cfg = config(r#"
+            [[alerts.routes]]
+            channel = 1
+            [alerts.routes.earthquakes]
+            min_magnitude = 3.0
+        "#);
+        let err = cfg.validate().expect_err("no points").to_string();
+        assert!(err.contains("earthquakes need points"));
+    }
+
+    #[test]
+    fn overnight_window_follows_spring_forward() {
+        let q = quiet_hours(OVERNIGHT);
+        // 06:30 PST the day before DST starts
+        assert!(q.is_quiet(utc("2026-03-07T14:30:00Z")));
+        // 06:30 PDT the morning DST starts
+        assert!(q.is_quiet(utc("2026-03-08T13:30:00Z")));
+        // 07:30 PDT, which would still be 06:30 on a fixed PST offset
+        assert!(!q.is_quiet(utc("2026-03-08T14:30:00Z")));
+    }
+
+    #[test]
+    fn overnight_window_follows_fall_back() {
+        let q = quiet_hours(OVERNIGHT);
+        // 06:30 PST the morning DST ends
+        assert!(q.is_quiet(utc("2026-11-01T14:30:00Z")));
+        // 07:00 PST, which would be 08:00 on a fixed PDT offset
+        assert!(!q.is_quiet(utc("2026-11-01T15:00:00Z")));
+        // 22:00 PST that evening
+        assert!(q.is_quiet(utc("2026-11-02T06:00:00Z")));
+        assert!(!q.is_quiet(utc("2026-11-02T05:59:00Z")));
+    }
+
+    #[test]
+    fn repeated_hour_is_quiet_both_times() {
+        let q = quiet_hours(r#"
+            timezone = "America/Los_Angeles"
+            [[windows]]
+            start = "01:00"
+            end = "02:00"
+        "#);
+        // 01:30 PDT, then 01:30 PST an hour later
+        assert!(q.is_quiet(utc("2026-11-01T08:30:00Z")));
+        assert!(q.is_quiet(utc("2026-11-01T09:30:00Z")));
+        assert!(!q.is_quiet(utc("2026-11-01T10:00:00Z")));
+    }
+
+    #[test]
+    fn skipped_hour_is_never_quiet() {
+        let q = quiet_hours(r#"
+            timezone = "America/Los_Angeles"
+            [[windows]]
+            start = "02:00"
+            end = "03:00"
+        "#);
+        // 01:59 PST is followed directly by 03:00 PDT
+        assert!(!q.is_quiet(utc("2026-03-08T09:59:00Z")));
+        assert!(!q.is_quiet(utc("2026-03-08T10:00:00Z")));
+        // The window still applies the day before
+        assert!(q.is_quiet(utc("2026-03-07T10:30:00Z")));
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_the_day_it_starts() {
+        let q = quiet_hours(r#"
+            timezone = "UTC"
+            [[windows]]
+            start = "22:00"
+            end = "07:00"
+            days = ["Fri", "Sat"]
+        "#);
+        // Friday night into Saturday morning
+        assert!(q.is_quiet(utc("2026-10-16T23:00:00Z")));
+        assert!(q.is_quiet(utc("2026-10-17T06:00:00Z")));
+        // Saturday night into Sunday morning
+        assert!(q.is_quiet(utc("2026-10-18T06:00:00Z")));
+        // Sunday night into Monday morning
+        assert!(!q.is_quiet(utc("2026-10-18T23:00:00Z")));
+        assert!(!q.is_quiet(utc("2026-10-19T06:00:00Z")));
+    }
+
+    #[test]
+    fn any_window_makes_it_quiet() {
+        let q = quiet_hours(r#"
+            timezone = "Europe/London"
+            [[windows]]
+            start = "12:00"
+            end = "13:00"
+            [[windows]]
+            start = "23:00"
+            end = "06:00"
+            days = ["Sun"]
+        "#);
+        // 12:30 BST on a Saturday
+        assert!(q.is_quiet(utc("2026-10-17T11:30:00Z")));
+        // 23:30 BST on Saturday, the overnight window only starts on Sunday
+        assert!(!q.is_quiet(utc("2026-10-17T22:30:00Z")));
+        // 23:30 BST on Sunday
+        assert!(q.is_quiet(utc("2026-10-18T22:30:00Z")));
+    }
+
+    #[test]
+    fn start_and_end_are_a_daily_window() {
+        let q = quiet_hours(r#"
+            timezone = "America/Los_Angeles"
+            start = "22:00"
+            end = "07:00"
+        "#);
+        // 23:00 PDT, then 08:00 PDT
+        assert!(q.is_quiet(utc("2026-10-17T06:00:00Z")));
+        assert!(!q.is_quiet(utc("2026-10-17T15:00:00Z")));
+        assert_eq!(q.to_string(), "22:00–07:00 America/Los_Angeles");
+    }
+
+    #[test]
+    fn start_and_end_need_a_timezone() {
+        let err = toml::from_str::<QuietHours>(r#"
+            start = "22:00"
+            end = "07:00"
+        "#).expect_err("no timezone").to_string();
+        assert!(err.contains("quiet hours need a `timezone` for `start` and `end`"), "{err}");
+    }
+
+    fn digest(toml: &str) -> DigestSchedule {
+        toml::from_str(toml).expect("valid digest schedule")
+    }
+
+    #[test]
+    fn daily_digests_are_due_each_morning() {
+        let d = digest(r#"
+            timezone = "America/Los_Angeles"
+            times = ["07:00"]
+        "#);
+        // 05:00 PDT, then right as the digest is posted
+        assert_eq!(d.next_after(utc("2026-10-17T12:00:00Z")), Some(utc("2026-10-17T14:00:00Z")));
+        assert_eq!(d.next_after(utc("2026-10-17T14:00:00Z")), Some(utc("2026-10-18T14:00:00Z")));
+    }
+
+    #[test]
+    fn weekly_digests_wait_for_their_day() {
+        let d = digest(r#"
+            timezone = "America/Los_Angeles"
+            times = ["07:00"]
+            days = ["Mon"]
+        "#);
+        // Saturday morning to Monday morning
+        assert_eq!(d.next_after(utc("2026-10-17T15:00:00Z")), Some(utc("2026-10-19T14:00:00Z")));
+    }
+
+    #[test]
+    fn skipped_digest_times_are_posted_an_hour_later() {
+        let d = digest(r#"
+            timezone = "America/Los_Angeles"
+            times = ["02:30"]
+        "#);
+        // 02:30 doesn't exist the morning DST starts, so it's 03:30 PDT instead
+        assert_eq!(d.next_after(utc("2026-03-08T08:00:00Z")), Some(utc("2026-03-08T10:30:00Z")));
+    }
+}
@@ -0,0 +1,409 @@
+//! Checks the config for mistakes, reporting every one found
+//! along with the key and line of the file it is on.
+
+use super::{AlertFilter, AlertRoute, CONFIG_FILE, Config, QuietHours};
+use crate::alerts::{Point, is_zone_code};
+use std::{collections::{HashMap, HashSet}, fmt};
+
+/// Discord IDs are snowflakes, which are never this small
+const MIN_SNOWFLAKE: u64 = 1 << 22;
+
+/// The highest value on the AQI scale
+const MAX_AQI: i32 = 500;
+
+/// One step of the path to a config key.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Path to a config key, ex "alerts.routes[0].areas[1]".
+/// Indexes start at 0, so `alerts.routes[0]` is the first route.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyPath(Vec<Segment>);
+
+impl KeyPath {
+    fn key(&self, key: &str) -> Self {
+        let mut path = self.clone();
+        path.0.push(Segment::Key(key.to_string()));
+        path
+    }
+
+    fn index(&self, index: usize) -> Self {
+        let mut path = self.clone();
+        path.0.push(Segment::Index(index));
+        path
+    }
+}
+
+impl fmt::Display for KeyPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                Segment::Key(key) if i == 0 => write!(f, "{key}")?,
+                Segment::Key(key) => write!(f, ".{key}")?,
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A single mistake in the config.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    /// Key the problem is with, empty if it isn't about any one key
+    pub path: KeyPath,
+    /// 1-based line of the file, if known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Every problem found in the config file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigError {
+    pub file: String,
+    pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for ConfigError {
+    /// One problem per line, ex "config.toml:12: alerts.routes[0].channel: `0` isn't a channel ID"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", self.file)?;
+            if let Some(line) = problem.line {
+                write!(f, ":{line}")?;
+            }
+            write!(f, ": ")?;
+            if !problem.path.0.is_empty() {
+                write!(f, "{}: ", problem.path)?;
+            }
+            write!(f, "{}", problem.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Line a byte offset into the file is on.
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+impl ConfigError {
+    /// The config file couldn't be read at all.
+    pub fn unreadable(e: std::io::Error) -> Self {
+        let problem = Problem { path: KeyPath::default(), line: None, message: format!("can't be read: {e}") };
+        Self { file: CONFIG_FILE.to_string(), problems: vec![problem] }
+    }
+
+    /// The config file isn't valid TOML, or doesn't fit the config structs.
+    pub fn from_toml(e: &toml::de::Error, source: &str) -> Self {
+        let problem = Problem {
+            path: KeyPath::default(),
+            line: e.span().map(|span| line_of(source, span.start)),
+            message: e.message().trim().to_string(),
+        };
+        Self { file: CONFIG_FILE.to_string(), problems: vec![problem] }
+    }
+
+    /// Fill in the line of each problem from the text of the file. Keys that
+    /// aren't in the file, such as left out tables, use the closest key that is.
+    pub fn locate(mut self, source: &str) -> Self {
+        let Ok(doc) = toml_edit::Document::parse(source) else {
+            return self;
+        };
+        for problem in &mut self.problems {
+            let mut item = doc.as_item();
+            let mut span = None;
+            for segment in &problem.path.0 {
+                let next = match segment {
+                    Segment::Key(key) => item.get(key),
+                    Segment::Index(index) => item.get(index),
+                };
+                let Some(next) = next else {
+                    break;
+                };
+                item = next;
+                span = item.span().or(span);
+            }
+            problem.line = span.map(|span| line_of(source, span.start));
+        }
+        self
+    }
+}
+
+/// Collects problems as the config is checked.
+#[derive(Default)]
+struct Problems(Vec<Problem>);
+
+impl Problems {
+    fn push(&mut self, path: KeyPath, message: impl Into<String>) {
+        self.0.push(Problem { path, line: None, message: message.into() });
+    }
+
+    fn into_result(self) -> Result<(), ConfigError> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError { file: CONFIG_FILE.to_string(), problems: self.0 })
+        }
+    }
+
+    fn check_snowflake(&mut self, path: KeyPath, id: u64, kind: &str) {
+        if id < MIN_SNOWFLAKE {
+            self.push(path, format!("`{id}` isn't a {kind} ID, copy it from Discord with developer mode on"));
+        }
+    }
+
+    fn check_point(&mut self, path: KeyPath, point: Point) {
+        if !(-90.0..=90.0).contains(&point.lat) || !(-180.0..=180.0).contains(&point.lon) {
+            self.push(path, format!("`{point}` isn't a latitude and longitude"));
+        }
+    }
+
+    fn check_web_address(&mut self, path: KeyPath, url: &str) {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            self.push(path, format!("`{url}` isn't a web address"));
+        }
+    }
+
+    fn check_not_empty(&mut self, path: KeyPath, value: &str) {
+        if value.is_empty() {
+            self.push(path, "can't be empty");
+        }
+    }
+
+    fn check_filter(&mut self, path: &KeyPath, filter: &AlertFilter) {
+        let mut both: Vec<&String> = filter.events.intersection(&filter.exclude_events).collect();
+        both.sort();
+        for event in both {
+            self.push(path.key("exclude_events"), format!("`{event}` is also in `events`, so it is never sent"));
+        }
+    }
+
+    fn check_quiet_hours(&mut self, path: KeyPath, quiet_hours: &QuietHours) {
+        if quiet_hours.windows.is_empty() {
+            self.push(path.key("windows"), "quiet hours need at least one window");
+        }
+        for (i, window) in quiet_hours.windows.iter().enumerate() {
+            if window.start == window.end {
+                self.push(path.key("windows").index(i), "start and end are the same, so the window is empty");
+            }
+        }
+    }
+
+    fn check_route(&mut self, path: KeyPath, route: &AlertRoute) {
+        self.check_snowflake(path.key("channel"), route.channel, "channel");
+        for (i, area) in route.areas.iter().enumerate() {
+            if !is_zone_code(area) {
+                self.push(path.key("areas").index(i), format!("`{area}` isn't a zone or county code (ex CAC007)"));
+            }
+        }
+        for (i, &point) in route.points.iter().enumerate() {
+            self.check_point(path.key("points").index(i), point);
+        }
+        for (i, feed) in route.feeds.iter().enumerate() {
+            self.check_web_address(path.key("feeds").index(i).key("url"), &feed.url);
+        }
+
+        if let Some(watch) = &route.earthquakes {
+            let path = path.key("earthquakes");
+            if route.earthquake_points().is_empty() {
+                self.push(path.key("points"), "earthquakes need points to measure distance from");
+            }
+            for (i, &point) in watch.points.iter().enumerate() {
+                self.check_point(path.key("points").index(i), point);
+            }
+            if watch.min_magnitude < 0.0 {
+                self.push(path.key("min_magnitude"), "can't be below 0");
+            }
+            if watch.max_distance_km <= 0.0 {
+                self.push(path.key("max_distance_km"), "must be more than 0");
+            }
+        }
+        if let Some(watch) = &route.air_quality {
+            let path = path.key("air_quality");
+            if route.air_quality_points().is_empty() {
+                self.push(path.key("points"), "air quality needs points to check");
+            }
+            for (i, &point) in watch.points.iter().enumerate() {
+                self.check_point(path.key("points").index(i), point);
+            }
+            if !(0..=MAX_AQI).contains(&watch.min_aqi) {
+                self.push(path.key("min_aqi"), format!("must be between 0 and {MAX_AQI}"));
+            }
+        }
+
+        if route.areas.is_empty() && route.points.is_empty() && route.feeds.is_empty()
+            && route.earthquakes.is_none() && route.air_quality.is_none() {
+            self.push(path.clone(), "needs areas, points, feeds, earthquakes or air quality to get alerts for");
+        }
+
+        self.check_filter(&path, &route.filter);
+        for (i, mention) in route.mentions.iter().enumerate() {
+            self.check_snowflake(path.key("mentions").index(i).key("role"), mention.role, "role");
+        }
+        if let Some(quiet_hours) = &route.quiet_hours {
+            self.check_quiet_hours(path.key("quiet_hours"), quiet_hours);
+        }
+        if let Some(digest) = &route.digest {
+            let times: HashSet<_> = digest.times.iter().collect();
+            if times.is_empty() {
+                self.push(path.key("digest").key("times"), "digest needs at least one time to post at");
+            } else if times.len() != digest.times.len() {
+                self.push(path.key("digest").key("times"), "lists the same time more than once");
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Check for mistakes that would otherwise quietly stop alerts from arriving.
+    /// Problems don't have lines until [`ConfigError::locate`] is given the file.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Problems::default();
+
+        let bot = KeyPath::default().key("bot");
+        problems.check_not_empty(bot.key("prefix"), &self.bot.prefix);
+        for (i, prefix) in self.bot.additional_prefixes.iter().enumerate() {
+            problems.check_not_empty(bot.key("additional_prefixes").index(i), prefix);
+        }
+
+        let fun = KeyPath::default().key("fun");
+        problems.check_web_address(fun.key("fact_url"), &self.fun.fact_url);
+        problems.check_web_address(fun.key("cat_fact_url"), &self.fun.cat_fact_url);
+        if self.fun.boop_timeout.is_zero() {
+            problems.push(fun.key("boop_timeout"), "must be more than 0 seconds");
+        }
+        if !(0.0..=1.0).contains(&self.fun.mock_uppercase_chance) {
+            problems.push(fun.key("mock_uppercase_chance"), "must be between 0 and 1");
+        }
+
+        problems.check_not_empty(KeyPath::default().key("storage").key("db_path"), &self.storage.db_path);
+
+        let alerts = KeyPath::default().key("alerts");
+
+        if self.alerts.check_interval.is_zero() {
+            problems.push(alerts.key("check_interval"), "must be more than 0 seconds");
+        }
+        problems.check_filter(&alerts, &self.alerts.filter);
+        for (i, route) in self.alerts.routes.iter().enumerate() {
+            problems.check_route(alerts.key("routes").index(i), route);
+        }
+        if let Some(quiet_hours) = &self.quiet_hours {
+            problems.check_quiet_hours(KeyPath::default().key("quiet_hours"), quiet_hours);
+        }
+
+        problems.into_result()
+    }
+
+    /// Check every zone in the routes is in `names`, the zones NWS knows about.
+    pub fn check_zones_known(&self, names: &HashMap<String, String>) -> Result<(), ConfigError> {
+        let mut problems = Problems::default();
+        let routes = KeyPath::default().key("alerts").key("routes");
+        for (i, route) in self.alerts.routes.iter().enumerate() {
+            for (j, area) in route.areas.iter().enumerate() {
+                if !names.contains_key(area) {
+                    problems.push(routes.index(i).key("areas").index(j), format!("NWS has no zone named `{area}`"));
+                }
+            }
+        }
+        problems.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::parse_config;
+
+    fn problems(source: &str) -> Vec<String> {
+        let err = parse_config(source).expect_err("invalid config");
+        err.to_string().lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn every_problem_is_reported_with_its_line_and_key() {
+        let problems = problems(r#"[alerts]
+check_interval = 0
+
+[[alerts.routes]]
+channel = 0
+areas = ["CAC007", "nope"]
+
+[[alerts.routes]]
+channel = 1472708201211494562
+[alerts.routes.quiet_hours]
+timezone = "UTC"
+[[alerts.routes.quiet_hours.windows]]
+start = "07:00"
+end = "07:00"
+"#);
+        assert_eq!(problems, [
+            "config.toml:2: alerts.check_interval: must be more than 0 seconds",
+            "config.toml:5: alerts.routes[0].channel: `0` isn't a channel ID, copy it from Discord with developer mode on",
+            "config.toml:6: alerts.routes[0].areas[1]: `nope` isn't a zone or county code (ex CAC007)",
+            "config.toml:8: alerts.routes[1]: needs areas, points, feeds, earthquakes or air quality to get alerts for",
+            "config.toml:12: alerts.routes[1].quiet_hours.windows[0]: start and end are the same, so the window is empty",
+        ]);
+    }
+
+    #[test]
+    fn left_out_keys_point_at_their_table() {
+        let problems = problems(r#"[alerts]
+check_interval = 60
+
+[[alerts.routes]]
+channel = 1472708201211494562
+[alerts.routes.earthquakes]
+min_magnitude = -1
+"#);
+        assert_eq!(problems, [
+            "config.toml:6: alerts.routes[0].earthquakes.points: earthquakes need points to measure distance from",
+            "config.toml:7: alerts.routes[0].earthquakes.min_magnitude: can't be below 0",
+        ]);
+    }
+
+    #[test]
+    fn parse_errors_have_a_line() {
+        let problems = problems("[alerts]\ncheck_interval = \"soon\"\nroutes = []\n");
+        let [problem] = &problems[..] else {
+            panic!("expected one problem, got {problems:?}");
+        };
+        assert!(problem.starts_with("config.toml:2: "), "{problem}");
+    }
+
+    #[test]
+    fn bot_settings_are_checked() {
+        let problems = problems(r#"[bot]
+prefix = ""
+
+[fun]
+cat_fact_url = "cat-facts.json"
+mock_uppercase_chance = 1.5
+"#);
+        assert_eq!(problems, [
+            "config.toml:2: bot.prefix: can't be empty",
+            "config.toml:5: fun.cat_fact_url: `cat-facts.json` isn't a web address",
+            "config.toml:6: fun.mock_uppercase_chance: must be between 0 and 1",
+        ]);
+    }
+
+    #[test]
+    fn valid_configs_pass() {
+        let config = parse_config(r#"[alerts]
+check_interval = 60
+
+[[alerts.routes]]
+channel = 1472708201211494562
+areas = ["CAC007"]
+mentions = [{ role = 1472708201211494563 }]
+"#);
+        assert!(config.is_ok(), "{config:?}");
+    }
+}
@@ -1,5 +1,7 @@
+use crate::alerts::{ArchivedAlert, CheckIns, PendingDigest, Posted, QuietQueue, Subscription};
+use poise::serenity_prelude::{ChannelId, MessageId, UserId};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
@@ -8,6 +10,24 @@ use tracing::{info, warn};
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Database {
     pub immuwune: HashSet<String>,
+    /// NWS alerts that have already been handled, keyed by CAP identifier
+    #[serde(default)]
+    pub alerts: HashMap<String, Posted>,
+    /// Members getting alerts by DM
+    #[serde(default)]
+    pub subscriptions: HashMap<UserId, Subscription>,
+    /// Every alert that has been delivered, oldest first
+    #[serde(default)]
+    pub history: Vec<ArchivedAlert>,
+    /// Members' answers to the check-in buttons, keyed by alert message
+    #[serde(default)]
+    pub check_ins: HashMap<MessageId, CheckIns>,
+    /// Alerts waiting for the next digest, keyed by the channel of a route in digest mode
+    #[serde(default)]
+    pub digests: HashMap<ChannelId, PendingDigest>,
+    /// Alerts held back until quiet hours end
+    #[serde(default)]
+    pub quiet_queue: QuietQueue,
 }
 
 impl Database {
@@ -1,19 +1,20 @@
 mod alerts;
+mod chunk;
 mod commands;
 mod config;
 mod db;
-use config::load_config;
+use config::{Config, load_resolved_config};
 use db::Database;
 
 use dotenvy::dotenv;
 use poise::serenity_prelude as serenity;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env::var,
-    sync::Arc,
-    time::{Duration, Instant},
+    sync::{Arc, atomic::{AtomicBool, Ordering}},
+    time::Instant,
 };
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, watch};
 use tracing::{error, info, warn};
 
 // Types used by all command functions
@@ -24,8 +25,20 @@ type Context<'a> = poise::Context<'a, Data, Error>;
 pub struct Data {
     votes: Mutex<HashMap<String, u32>>,
     start_time: Instant,
-    db: Mutex<Database>,
+    db: Arc<Mutex<Database>>,
     db_path: String,
+    /// Latest config, updated whenever config.toml is reloaded
+    config: watch::Receiver<Config>,
+    /// Pushes config changes made by commands to the alerts task
+    config_tx: watch::Sender<Config>,
+    /// Set once the alerts task starts, which waits for a valid config.toml
+    alerts_running: Arc<AtomicBool>,
+    /// Held while a command edits config.toml
+    config_lock: Mutex<()>,
+    /// Shared by the alerts task and the weather commands
+    http_client: reqwest::Client,
+    /// Latest health of each alerts provider
+    feed_health: watch::Receiver<BTreeMap<alerts::Provider, alerts::FeedHealth>>,
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
@@ -45,15 +58,60 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     }
 }
 
+/// Text command prefixes are read from the config on every message, so they change on reload.
+fn strip_prefix<'a>(
+    _ctx: &'a serenity::Context,
+    msg: &'a serenity::Message,
+    data: &'a Data,
+) -> poise::BoxFuture<'a, Result<Option<(&'a str, &'a str)>, Error>> {
+    Box::pin(async move { Ok(data.config.borrow().bot.strip_prefix(&msg.content)) })
+}
+
+/// Validate config.toml and print every problem with it, without connecting to Discord.
+/// Returns the exit code for `--check-config`.
+async fn check_config() -> i32 {
+    match load_resolved_config(&reqwest::Client::new()).await {
+        Ok(config) => {
+            println!("config.toml is valid, with {} alert route(s)", config.alerts.routes.len());
+            0
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
     dotenv().ok();
 
+    if std::env::args().skip(1).any(|arg| arg == "--check-config") {
+        std::process::exit(check_config().await);
+    }
+
+    let http_client = reqwest::Client::new();
+
+    // Only run alerts if the config was provided & parsed correctly,
+    // otherwise fall back to the default settings until it's fixed
+    let (config, run_alerts) = match load_resolved_config(&http_client).await {
+        Ok(config) => {
+            info!("Loaded config.toml");
+            (config, true)
+        }
+        Err(e) => {
+            warn!("Running with default settings and without alerts until config.toml is fixed:\n{e}");
+            (Config::default(), false)
+        }
+    };
+
     // FrameworkOptions contains all of poise's configuration option in one struct
     // Every option can be omitted to use its default value
     let options = poise::FrameworkOptions {
         commands: vec![
+            commands::alerts::alerts(),
+
             commands::fun::ban(),
             commands::fun::catfact(),
             commands::fun::boop(),
@@ -86,16 +144,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             commands::roles::del(),
             commands::roles::list_roles(),
             commands::roles::my_roles(),
+
+            commands::weather::weather(),
         ],
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some("!".into()),
+            stripped_dynamic_prefix: Some(strip_prefix),
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
-                Duration::from_secs(3600),
+                config.bot.edit_tracker_window,
             ))),
-            additional_prefixes: vec![
-                poise::Prefix::Literal("hey bot,"),
-                poise::Prefix::Literal("hey bot"),
-            ],
             ..Default::default()
         },
         // The global error handler for all error cases that may occur
@@ -136,32 +192,59 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 info!("Logged in as {}", _ready.user.name);
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
 
-                match load_config() {
-                    // Only spawn alerts task if the config was provided & parsed correctly
-                    Ok(config) => {
-                        info!("Loaded config.toml");
-                        let http = ctx.http.clone();
-                        let (tx, rx) = tokio::sync::watch::channel(config.clone());
-                        tokio::spawn(async move {
-                            let _ = alerts::alerts(http, config, rx).await;
-                        });
-                        tokio::spawn(async move {
-                            let _ = config::watch_config(tx).await;
-                        });
-                    },
-                    Err(e) => warn!("Running without NWS alerts due to toml error: {e}"),
-                }
-
-                let db_path = String::from("db.json");
-                let db = Mutex::new(
+                let db_path = config.storage.db_path.clone();
+                let db = Arc::new(Mutex::new(
                     Database::load(&db_path).await?,
-                );
+                ));
+
+                // Check-in buttons stay on alerts posted before a restart, so always listen for them
+                tokio::spawn(alerts::collect_check_ins(ctx.clone(), db.clone(), db_path.clone()));
+
+                let (tx, mut rx) = watch::channel(config.clone());
+                let commands_rx = tx.subscribe();
+                let config_tx = tx.clone();
+                let (health_tx, feed_health) = watch::channel(BTreeMap::new());
+                let alerts_running = Arc::new(AtomicBool::new(false));
+
+                let http = ctx.http.clone();
+                let running = alerts_running.clone();
+                let alerts_db = db.clone();
+                let alerts_db_path = db_path.clone();
+                let alerts_client = http_client.clone();
+                tokio::spawn(async move {
+                    let config = if run_alerts {
+                        config
+                    } else {
+                        // Only a valid config.toml is reloaded, so start alerts with the first one
+                        if rx.changed().await.is_err() {
+                            return;
+                        }
+                        info!("config.toml is fixed, starting alerts");
+                        rx.borrow_and_update().clone()
+                    };
+                    running.store(true, Ordering::Relaxed);
+                    let _ = alerts::alerts(
+                        http, config, rx, alerts_db, alerts_db_path, alerts_client, health_tx,
+                    ).await;
+                });
+
+                // Also starts alerts once config.toml is fixed, and reloads the other settings
+                let watch_client = http_client.clone();
+                tokio::spawn(async move {
+                    let _ = config::watch_config(tx, watch_client).await;
+                });
 
                 Ok(Data {
                     votes: Mutex::new(HashMap::new()),
                     start_time: Instant::now(),
                     db,
                     db_path,
+                    config: commands_rx,
+                    config_tx,
+                    alerts_running,
+                    config_lock: Mutex::new(()),
+                    http_client,
+                    feed_health,
                 })
             })
         })